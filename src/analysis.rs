@@ -0,0 +1,118 @@
+//! Classifying how a pattern behaves over time.
+
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeriodKind {
+    /// Every cell died.
+    Empty = 0,
+    StillLife = 1,
+    Oscillator = 2,
+    /// An earlier state recurred translated.
+    Spaceship = 3,
+    /// No earlier state recurred within the generations searched.
+    NotPeriodic = 4,
+}
+
+/// Outcome of `Universe::detect_period`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Periodicity {
+    pub kind: PeriodKind,
+    /// Generations per cycle; 0 unless the pattern is periodic.
+    pub period: u32,
+    /// Generation at which the cycle is first entered, 0 for a pattern that is periodic from the
+    /// start. For an empty result, the generation at which the last cell died.
+    pub start: u32,
+    /// Displacement per cycle, in the universe's coordinates (`y` increasing downwards).
+    #[wasm_bindgen(skip)]
+    pub dx: i64,
+    #[wasm_bindgen(skip)]
+    pub dy: i64,
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl Periodicity {
+    pub fn periodic(start: u32, period: u32, dx: i64, dy: i64) -> Periodicity {
+        let kind = match (period, dx, dy) {
+            (1, 0, 0) => PeriodKind::StillLife,
+            (_, 0, 0) => PeriodKind::Oscillator,
+            _ => PeriodKind::Spaceship,
+        };
+        Periodicity {
+            kind,
+            period,
+            start,
+            dx,
+            dy,
+        }
+    }
+
+    pub fn empty(start: u32) -> Periodicity {
+        Periodicity {
+            kind: PeriodKind::Empty,
+            period: 0,
+            start,
+            dx: 0,
+            dy: 0,
+        }
+    }
+
+    pub fn not_periodic() -> Periodicity {
+        Periodicity {
+            kind: PeriodKind::NotPeriodic,
+            period: 0,
+            start: 0,
+            dx: 0,
+            dy: 0,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Periodicity {
+    /// `dx` as a JS number.
+    #[wasm_bindgen(getter = dx)]
+    pub fn js_dx(&self) -> f64 {
+        self.dx as f64
+    }
+
+    /// `dy` as a JS number.
+    #[wasm_bindgen(getter = dy)]
+    pub fn js_dy(&self) -> f64 {
+        self.dy as f64
+    }
+
+    /// Speed of a spaceship in the usual notation, such as `c/4 diagonal`, `2c/5 orthogonal` or
+    /// `(2,1)c/6 oblique`, reduced to lowest terms. Empty for anything that does not move.
+    pub fn speed(&self) -> String {
+        if self.kind != PeriodKind::Spaceship {
+            return String::new();
+        }
+        let (a, b) = (self.dx.unsigned_abs(), self.dy.unsigned_abs());
+        let (a, b) = (a.max(b), a.min(b));
+        let divisor = gcd(gcd(a, b), self.period as u64);
+        let (a, b, period) = (a / divisor, b / divisor, self.period as u64 / divisor);
+        let (distance, direction) = if b == 0 {
+            (a.to_string(), "orthogonal")
+        } else if a == b {
+            (a.to_string(), "diagonal")
+        } else {
+            (format!("({},{})", a, b), "oblique")
+        };
+        let distance = if distance == "1" { "" } else { &distance };
+        if period == 1 {
+            format!("{}c {}", distance, direction)
+        } else {
+            format!("{}c/{} {}", distance, period, direction)
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Helpers for the plaintext `.cells` pattern format.
+//!
+//! Lines starting with `!` are comments; every other line is a row of cells, `.` for dead and
+//! `O` (or `*`) for alive. Rows run downwards, i.e. towards increasing `y`.
+
+/// A parsed plaintext pattern.
+pub struct Plaintext {
+    /// Comment lines including their leading `!`.
+    pub comments: Vec<String>,
+    /// Live cells relative to the pattern's top-left corner.
+    pub cells: Vec<(i32, i32)>,
+    /// Length of the longest row and number of rows, trailing dead cells included.
+    pub width: i32,
+    pub height: i32,
+}
+
+pub fn parse(text: &str) -> Result<Plaintext, String> {
+    let mut comments = vec![];
+    let mut cells = vec![];
+    let mut y = 0;
+    let mut width = 0;
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.starts_with('!') {
+            comments.push(line.to_string());
+            continue;
+        }
+        for (x, c) in line.chars().enumerate() {
+            match c {
+                '.' => {}
+                'O' | 'o' | '*' => cells.push((x as i32, y)),
+                _ => {
+                    return Err(format!(
+                        "line {}, column {}: unexpected character '{}' in plaintext pattern",
+                        line_no + 1,
+                        x + 1,
+                        c
+                    ))
+                }
+            }
+        }
+        width = width.max(line.chars().count() as i32);
+        y += 1;
+    }
+    Ok(Plaintext {
+        comments,
+        cells,
+        width,
+        height: y,
+    })
+}
+
+/// Write live cells (relative to the top-left corner) as plaintext.
+///
+/// Comments written with another format's prefix, such as RLE's `#C`, are rewritten to `!`.
+pub fn write(cells: &[(i64, i64)], width: i64, height: i64, comments: &[String]) -> String {
+    let mut out = String::new();
+    for comment in comments {
+        if comment.starts_with('!') {
+            out.push_str(comment);
+        } else {
+            out.push('!');
+            out.push_str(comment.trim_start_matches('#').get(1..).unwrap_or(""));
+        }
+        out.push('\n');
+    }
+
+    let mut rows = vec![vec![b'.'; width.max(0) as usize]; height.max(0) as usize];
+    for &(x, y) in cells {
+        rows[y as usize][x as usize] = b'O';
+    }
+    for row in rows {
+        let len = row.iter().rposition(|&c| c == b'O').map_or(row.len().min(1), |i| i + 1);
+        out.push_str(std::str::from_utf8(&row[..len]).unwrap());
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,286 @@
+//! Object census: splitting a universe into separate objects and identifying each one.
+//!
+//! Cells belong to the same object when they lie within two cells of each other, the range
+//! over which they can influence each other in one generation. Each object is then run on its
+//! own to classify it, and B3/S23 objects are named by matching their canonical form (the
+//! smallest over all phases and orientations) against a small library of common objects.
+
+use crate::analysis::{PeriodKind, Periodicity};
+use crate::rle::{self, RleLimits};
+use crate::rule::Rule;
+use crate::Universe;
+use js_sys::Array;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use wasm_bindgen::prelude::*;
+
+/// Objects larger or slower than this are never looked up in the library, which holds
+/// nothing that big and would be costly to canonicalise against.
+const MAX_NAMED_POPULATION: usize = 64;
+const MAX_NAMED_PERIOD: u32 = 32;
+
+/// Common B3/S23 objects, each in one phase and orientation.
+const LIBRARY: &[(&str, &str)] = &[
+    ("block", "2o$2o!"),
+    ("beehive", "b2o$o2bo$b2o!"),
+    ("loaf", "b2o$o2bo$bobo$2bo!"),
+    ("boat", "2o$obo$bo!"),
+    ("ship", "2o$obo$b2o!"),
+    ("tub", "bo$obo$bo!"),
+    ("pond", "b2o$o2bo$o2bo$b2o!"),
+    ("barge", "bo$obo$bobo$2bo!"),
+    ("long boat", "2o$obo$bobo$2bo!"),
+    ("mango", "b2o$o2bo$bo2bo$2b2o!"),
+    ("snake", "2obo$ob2o!"),
+    ("aircraft carrier", "2o$o2bo$2b2o!"),
+    ("eater 1", "2o$obo$2bo$2b2o!"),
+    ("blinker", "3o!"),
+    ("toad", "b3o$3o!"),
+    ("beacon", "2o$2o$2b2o$2b2o!"),
+    ("clock", "2bo$obo$bobo$bo!"),
+    ("pulsar", "2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!"),
+    ("pentadecathlon", "2bo4bo$2ob4ob2o$2bo4bo!"),
+    ("glider", "bo$2bo$3o!"),
+    ("LWSS", "bo2bo$o$o3bo$4o!"),
+    ("MWSS", "3bo$bo3bo$o$o4bo$5o!"),
+    ("HWSS", "3b2o$bo4bo$o$o5bo$6o!"),
+];
+
+type Cells = Vec<(i64, i64, usize)>;
+
+/// One object found by the census.
+#[derive(Debug, Clone)]
+pub struct CensusObject {
+    /// A library name such as `glider`, or a description such as `period 3 oscillator`.
+    pub name: String,
+    pub periodicity: Periodicity,
+    /// Top-left corner of the object's bounding box.
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+    pub population: usize,
+}
+
+/// The objects making up a universe, ordered by their top-left cell.
+#[wasm_bindgen]
+pub struct Census {
+    objects: Vec<CensusObject>,
+}
+
+impl Census {
+    pub fn objects(&self) -> &[CensusObject] {
+        &self.objects
+    }
+
+    /// Number of objects with each name, most common first.
+    pub fn counts(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for object in &self.objects {
+            *counts.entry(&object.name).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().map(|(name, n)| (name.to_string(), n)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+}
+
+#[wasm_bindgen]
+impl Census {
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// The name of object `index`, or `None` past the end of the census.
+    pub fn name(&self, index: usize) -> Option<String> {
+        self.objects.get(index).map(|o| o.name.clone())
+    }
+
+    pub fn kind(&self, index: usize) -> Option<PeriodKind> {
+        self.objects.get(index).map(|o| o.periodicity.kind)
+    }
+
+    pub fn period(&self, index: usize) -> Option<u32> {
+        self.objects.get(index).map(|o| o.periodicity.period)
+    }
+
+    /// Bounding boxes flattened as `[x, y, width, height, ...]`, one per object.
+    pub fn boxes(&self) -> Array {
+        self.objects
+            .iter()
+            .flat_map(|o| [o.x, o.y, o.width, o.height])
+            .map(|v| JsValue::from(v as f64))
+            .collect()
+    }
+
+    /// One `count name` line per distinct name, most common first.
+    pub fn summary(&self) -> String {
+        self.counts()
+            .iter()
+            .map(|(name, n)| format!("{} {}\n", n, name))
+            .collect()
+    }
+}
+
+/// Group cells into objects.
+fn split(cells: &[(i64, i64, usize)]) -> Vec<Cells> {
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let index: HashMap<(i64, i64), usize> = cells
+        .iter()
+        .enumerate()
+        .map(|(i, &(x, y, _))| ((x, y), i))
+        .collect();
+    let mut parent: Vec<usize> = (0..cells.len()).collect();
+    for (i, &(x, y, _)) in cells.iter().enumerate() {
+        for dy in -2..=2 {
+            for dx in -2..=2 {
+                if let Some(&j) = index.get(&(x + dx, y + dy)) {
+                    let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                    parent[a] = b;
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Cells> = HashMap::new();
+    for (i, &cell) in cells.iter().enumerate() {
+        groups.entry(find(&mut parent, i)).or_default().push(cell);
+    }
+    let mut objects: Vec<Cells> = groups.into_values().collect();
+    for object in objects.iter_mut() {
+        object.sort_by_key(|&(x, y, _)| (y, x));
+    }
+    objects.sort_by_key(|object| (object[0].1, object[0].0));
+    objects
+}
+
+/// A universe holding only `cells`, moved so that their bounding box starts at the origin.
+fn isolate(cells: &[(i64, i64, usize)], rule: Rule) -> Universe {
+    let min_x = cells.iter().map(|c| c.0).min().unwrap_or(0);
+    let min_y = cells.iter().map(|c| c.1).min().unwrap_or(0);
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.space.set_rule(rule);
+    for &(x, y, state) in cells {
+        uni.set_cell(x - min_x, y - min_y, state);
+    }
+    uni
+}
+
+/// The smallest of the eight orientations of `cells`, each moved to the origin and sorted.
+fn canonical(cells: &[(i64, i64, usize)]) -> Cells {
+    (0..8)
+        .map(|t| {
+            let mut oriented: Cells = cells
+                .iter()
+                .map(|&(x, y, state)| {
+                    let (x, y) = if t & 4 != 0 { (y, x) } else { (x, y) };
+                    let x = if t & 1 != 0 { -x } else { x };
+                    let y = if t & 2 != 0 { -y } else { y };
+                    (x, y, state)
+                })
+                .collect();
+            let min_x = oriented.iter().map(|c| c.0).min().unwrap_or(0);
+            let min_y = oriented.iter().map(|c| c.1).min().unwrap_or(0);
+            for cell in oriented.iter_mut() {
+                cell.0 -= min_x;
+                cell.1 -= min_y;
+            }
+            oriented.sort_unstable();
+            oriented
+        })
+        .min()
+        .unwrap()
+}
+
+/// The canonical form of a periodic object across all of its phases. Advances `uni`.
+fn signature(uni: &mut Universe, period: u32) -> Cells {
+    let mut best = canonical(&uni.live_cells());
+    for _ in 1..period {
+        uni.advance_by(1);
+        best = best.min(canonical(&uni.live_cells()));
+    }
+    best
+}
+
+fn library() -> &'static HashMap<Cells, &'static str> {
+    static LIBRARY_SIGNATURES: OnceLock<HashMap<Cells, &'static str>> = OnceLock::new();
+    LIBRARY_SIGNATURES.get_or_init(|| {
+        LIBRARY
+            .iter()
+            .map(|&(name, data)| {
+                let text = format!("x = 16, y = 16\n{}", data);
+                let pattern = rle::parse(&text, &RleLimits::default()).expect("library pattern");
+                let cells: Cells = pattern
+                    .cells()
+                    .iter()
+                    .map(|&(x, y, state)| (x as i64, y as i64, state))
+                    .collect();
+                let mut uni = isolate(&cells, Rule::life());
+                let period = uni.detect_period(MAX_NAMED_PERIOD).period;
+                (signature(&mut uni, period), name)
+            })
+            .collect()
+    })
+}
+
+fn describe(periodicity: &Periodicity, population: usize) -> String {
+    match periodicity.kind {
+        PeriodKind::StillLife => format!("{}-cell still life", population),
+        PeriodKind::Oscillator => format!("period {} oscillator", periodicity.period),
+        PeriodKind::Spaceship => format!("{} spaceship", periodicity.speed()),
+        PeriodKind::Empty | PeriodKind::NotPeriodic => "unknown".to_string(),
+    }
+}
+
+fn classify(cells: &[(i64, i64, usize)], rule: Rule, max_period: u32) -> CensusObject {
+    let mut uni = isolate(cells, rule);
+    let periodicity = uni.detect_period(max_period);
+    // Something that only settles down after a while isn't any one object yet.
+    let name = if periodicity.start > 0 {
+        "unknown".to_string()
+    } else if rule == Rule::life()
+        && cells.len() <= MAX_NAMED_POPULATION
+        && (1..=MAX_NAMED_PERIOD).contains(&periodicity.period)
+    {
+        match library().get(&signature(&mut uni, periodicity.period)) {
+            Some(name) => name.to_string(),
+            None => describe(&periodicity, cells.len()),
+        }
+    } else {
+        describe(&periodicity, cells.len())
+    };
+
+    let min_x = cells.iter().map(|c| c.0).min().unwrap();
+    let min_y = cells.iter().map(|c| c.1).min().unwrap();
+    let max_x = cells.iter().map(|c| c.0).max().unwrap();
+    let max_y = cells.iter().map(|c| c.1).max().unwrap();
+    CensusObject {
+        name,
+        periodicity,
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x + 1,
+        height: max_y - min_y + 1,
+        population: cells.len(),
+    }
+}
+
+/// Split `cells` into objects and classify each, searching up to `max_period` generations.
+pub fn take(cells: &[(i64, i64, usize)], rule: Rule, max_period: u32) -> Census {
+    let objects = split(cells)
+        .iter()
+        .map(|object| classify(object, rule, max_period))
+        .collect();
+    Census { objects }
+}
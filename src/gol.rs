@@ -1,157 +1,356 @@
-use bimap::BiMap;
-use std::hash::{Hash, Hasher};
-use std::fs::File;
-use std::time::Instant;
+use crate::rule::Rule;
+use crate::transform::Transform;
 use std::cmp;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
 
-
+/// Handle to a tree interned in a `Space`.
+///
+/// Nodes are indices into the `Space` arena. Leaves never get an arena slot: their state is
+/// stored directly in the ID with `LEAF_BIT` set, so a level-1 node's children are just states.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct ID(usize);
+pub struct ID(u32);
 
+const LEAF_BIT: u32 = 1 << 31;
+const EMPTY_SLOT: u32 = u32::MAX;
 
 impl ID {
-    pub fn fetch_from(self, sp: &Space) -> &QTree {
-        match sp.table.get_by_left(&self) {
-            Some(tree) => tree,
-            None => {
-                panic!("Found None, while trying to fetch tree with id={:?}", self)
-            }
-        }
+    fn leaf(state: usize) -> ID {
+        debug_assert!((state as u32) < LEAF_BIT, "leaf state out of range");
+        ID(LEAF_BIT | state as u32)
     }
 
-    pub fn fetch_node(self, sp: &Space) -> &Node {
-        if let QTree::Node(node) = self.fetch_from(sp) {
-            node
+    #[inline(always)]
+    pub fn is_leaf(self) -> bool {
+        self.0 & LEAF_BIT != 0
+    }
+
+    /// The cell state if this ID is a leaf.
+    #[inline(always)]
+    pub fn leaf_state(self) -> Option<usize> {
+        if self.is_leaf() {
+            Some((self.0 & !LEAF_BIT) as usize)
         } else {
-            let x = self.fetch_from(sp);
-            panic!(
-                "Fetch failed; found {:?} while looking for Node with id={:?}",
-                x, self
-            );
+            None
         }
     }
-}
 
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub enum QTree {
-    Node(Node),
-    Leaf(Leaf),
-}
+    #[inline(always)]
+    fn index(self) -> usize {
+        debug_assert!(!self.is_leaf(), "leaves have no arena slot");
+        self.0 as usize
+    }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Leaf(usize);
+    #[inline(always)]
+    pub fn fetch_node(self, sp: &Space) -> &Node {
+        match sp.nodes.get(self.0 as usize) {
+            Some(node) => node,
+            None => panic!("Fetch failed while looking for Node with id={:?}", self),
+        }
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub struct Node {
-    level: usize,
-    pop: usize,
-    next: Option<ID>,
     pub north_west: ID,
     pub north_east: ID,
     pub south_west: ID,
     pub south_east: ID,
+    pop: usize,
+    level: u32,
 }
 
-impl Eq for Node {}
-
-impl PartialEq for Node {
-    fn eq(&self, other: &Self) -> bool {
-        self.north_west == other.north_west && self.north_east == other.north_east && self.south_west == other.south_west && self.south_east== other.south_east
+impl Node {
+    #[inline(always)]
+    fn children(&self) -> [ID; 4] {
+        [self.north_west, self.north_east, self.south_west, self.south_east]
     }
 }
 
-impl Hash for Node {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.north_west.hash(state);
-        self.north_east.hash(state);
-        self.south_west.hash(state);
-        self.south_east.hash(state);
+#[inline(always)]
+fn hash_children(children: &[ID; 4]) -> usize {
+    let mut h: u64 = 0;
+    for c in children {
+        h = (h ^ c.0 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        h ^= h >> 29;
     }
+    h as usize
 }
 
-impl QTree {
-    #[inline(always)]
-    pub(crate) fn population(&self) -> usize {
-        match *self {
-            QTree::Node(ref i) => i.pop,
-            QTree::Leaf(c) => c.0 as usize,
+/// Hasher for the memo table; its keys are small integers, which SipHash handles needlessly slowly.
+#[derive(Default)]
+struct IdHasher(u64);
+
+impl Hasher for IdHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(b as u64);
         }
     }
 
-    pub(crate) fn level(&self) -> usize {
-        match *self {
-            QTree::Node(ref i) => i.level,
-            QTree::Leaf(_) => 0,
-        }
+    fn write_u8(&mut self, n: u8) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x9e37_79b9_7f4a_7c15);
     }
+
+    fn finish(&self) -> u64 {
+        self.0 ^ (self.0 >> 32)
+    }
+}
+
+type ResultMap = HashMap<(ID, u8), ID, BuildHasherDefault<IdHasher>>;
+
+/// A side of a tree, for `Space::bounding_box`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Edge {
+    West,
+    East,
+    South,
+    North,
 }
 
-pub struct Coord {
-    x: i32,
-    y: i32
+/// How `Space::combine` merges two trees.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SetOp {
+    /// Cells live in either tree; where both are, the second tree's state wins.
+    Union,
+    /// Cells live in both trees, keeping the first tree's state.
+    Intersect,
+    /// Cells live in the first tree but not the second.
+    Subtract,
+    /// Cells live in exactly one of the trees.
+    Xor,
 }
 
+impl SetOp {
+    fn apply(self, a: usize, b: usize) -> usize {
+        match self {
+            SetOp::Union if b != 0 => b,
+            SetOp::Union => a,
+            SetOp::Intersect if b != 0 => a,
+            SetOp::Subtract if b == 0 => a,
+            SetOp::Xor if a == 0 => b,
+            SetOp::Xor if b == 0 => a,
+            _ => 0,
+        }
+    }
+}
+
+/// Default number of nodes a `Space` may hold before it asks for a collection.
+pub const DEFAULT_GC_THRESHOLD: usize = 1 << 20;
+
+/// Hash-consed quadtree store.
+///
+/// `nodes` is an arena indexed directly by `ID`; `index` is an open-addressed hash set of arena
+/// indices keyed on the four child IDs, used only to intern new nodes. `results` memoizes
+/// `evolve_tree`, keyed by node and the step exponent actually applied to it.
 pub struct Space {
-    table: BiMap<ID, QTree>,
-    // root: Option<ID>,
+    nodes: Vec<Node>,
+    index: Vec<u32>,
+    results: ResultMap,
+    rule: Rule,
+    gc_threshold: usize,
+    live_after_gc: usize,
 }
 
 impl Space {
     pub fn new() -> Space {
-        let t: BiMap<ID, QTree> = BiMap::new();
         Space {
-            table: t,
-            // root: None
+            nodes: vec![],
+            index: vec![EMPTY_SLOT; 1 << 10],
+            results: ResultMap::default(),
+            rule: Rule::default(),
+            gc_threshold: DEFAULT_GC_THRESHOLD,
+            live_after_gc: 0,
+        }
+    }
+
+    /// Number of interned nodes currently in the arena.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Switch the rule used by `evolve_tree`, discarding every cached result.
+    pub fn set_rule(&mut self, rule: Rule) {
+        if rule == self.rule {
+            return;
+        }
+        self.rule = rule;
+        self.results.clear();
+    }
+
+    pub fn set_gc_threshold(&mut self, threshold: usize) {
+        self.gc_threshold = threshold;
+    }
+
+    /// Whether the arena has grown enough that a collection is worthwhile.
+    ///
+    /// The threshold is never allowed to sit below twice the size of the last surviving
+    /// set, otherwise a large live pattern would be re-collected on every call.
+    pub fn needs_gc(&self) -> bool {
+        self.nodes.len() > cmp::max(self.gc_threshold, 2 * self.live_after_gc)
+    }
+
+    #[inline(always)]
+    pub fn level(&self, id: ID) -> usize {
+        if id.is_leaf() {
+            0
+        } else {
+            id.fetch_node(self).level as usize
+        }
+    }
+
+    #[inline(always)]
+    pub fn population(&self, id: ID) -> usize {
+        match id.leaf_state() {
+            Some(state) => (state > 0) as usize,
+            None => id.fetch_node(self).pop,
         }
     }
 
-    fn get_id(&mut self, node: QTree) -> ID {
-        match self.table.get_by_right(&node) {
-            Some(id) => *id,
-            _ => {
-                let id = ID(self.table.len());
-                self.table.insert(id, node);
+    /// Mark-and-sweep collection of the node arena.
+    ///
+    /// Every node reachable from `roots` (through children and memoized results) survives;
+    /// everything else is dropped. Surviving nodes are compacted to the front of the arena in
+    /// their original order, so children keep lower IDs than their parents, and `roots` is
+    /// rewritten in place to the new IDs.
+    pub fn collect_garbage(&mut self, roots: &mut [ID]) {
+        let mut marked = vec![false; self.nodes.len()];
+        let mut stack: Vec<ID> = roots.iter().copied().filter(|id| !id.is_leaf()).collect();
+        // A result is only reachable through its key node, so keep marking until no surviving
+        // key brings in a new result.
+        while !stack.is_empty() {
+            while let Some(id) = stack.pop() {
+                if marked[id.index()] {
+                    continue;
+                }
+                marked[id.index()] = true;
+                stack.extend(id.fetch_node(self).children().iter().filter(|c| !c.is_leaf()));
+            }
+            stack.extend(
+                self.results
+                    .iter()
+                    .filter(|(&(key, _), result)| marked[key.index()] && !marked[result.index()])
+                    .map(|(_, &result)| result),
+            );
+        }
+
+        let mut remap: Vec<u32> = vec![EMPTY_SLOT; self.nodes.len()];
+        let relink = |remap: &[u32], id: ID| {
+            if id.is_leaf() {
                 id
+            } else {
+                debug_assert!(remap[id.index()] != EMPTY_SLOT, "marked node refers to an unmarked one");
+                ID(remap[id.index()])
+            }
+        };
+
+        let mut live = 0;
+        for old in 0..self.nodes.len() {
+            if !marked[old] {
+                continue;
+            }
+            let node = self.nodes[old];
+            let [north_west, north_east, south_west, south_east] =
+                node.children().map(|c| relink(&remap, c));
+            self.nodes[live] = Node {
+                north_west,
+                north_east,
+                south_west,
+                south_east,
+                ..node
+            };
+            remap[old] = live as u32;
+            live += 1;
+        }
+        self.nodes.truncate(live);
+        self.results = self
+            .results
+            .drain()
+            .filter(|&((key, _), _)| marked[key.index()])
+            .map(|((key, j), result)| ((relink(&remap, key), j), relink(&remap, result)))
+            .collect();
+        self.live_after_gc = live;
+        self.rebuild_index();
+
+        for root in roots.iter_mut() {
+            *root = relink(&remap, *root);
+        }
+    }
+
+    fn rebuild_index(&mut self) {
+        let capacity = cmp::max(1 << 10, (self.nodes.len() * 2).next_power_of_two());
+        self.index = vec![EMPTY_SLOT; capacity];
+        let mask = capacity - 1;
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut slot = hash_children(&node.children()) & mask;
+            while self.index[slot] != EMPTY_SLOT {
+                slot = (slot + 1) & mask;
             }
+            self.index[slot] = i as u32;
         }
     }
 
-    fn new_leaf(&mut self, state: usize) -> ID {
-        let node = QTree::Leaf(Leaf(state));
-        self.get_id(node)
+    pub fn new_leaf(&self, state: usize) -> ID {
+        ID::leaf(state)
     }
 
-    fn new_node(&mut self, nw_id: ID, ne_id: ID, sw_id: ID, se_id: ID) -> ID {
-        let children = (
-            nw_id.fetch_from(self),
-            ne_id.fetch_from(self),
-            sw_id.fetch_from(self),
-            se_id.fetch_from(self),
-        );
-        let n = match children {
-            (QTree::Node(nw), QTree::Node(ne), QTree::Node(sw), QTree::Node(se)) => 
-                Node {
-                    level: nw.level + 1,
-                    pop: nw.pop + ne.pop + sw.pop + se.pop,
-                    next: None,
-                    north_west: nw_id,
-                    north_east: ne_id,
-                    south_west: sw_id,
-                    south_east: se_id,
-                },
-            (QTree::Leaf(nw), QTree::Leaf(ne), QTree::Leaf(sw), QTree::Leaf(se)) => 
-                Node {
-                    level: 1,
-                    pop: [nw, ne, sw, se].iter().filter(|c| c.0 > 0).count() as usize,
-                    next: None,
-                    north_west: nw_id,
-                    north_east: ne_id,
-                    south_west: sw_id,
-                    south_east: se_id,
-                },
-            _ => panic!("Attempting to create a node containing leaves & nodes!"),
+    pub fn new_node(&mut self, nw_id: ID, ne_id: ID, sw_id: ID, se_id: ID) -> ID {
+        let children = [nw_id, ne_id, sw_id, se_id];
+        let mask = self.index.len() - 1;
+        let mut slot = hash_children(&children) & mask;
+        loop {
+            let entry = self.index[slot];
+            if entry == EMPTY_SLOT {
+                break;
+            }
+            if self.nodes[entry as usize].children() == children {
+                return ID(entry);
+            }
+            slot = (slot + 1) & mask;
+        }
+
+        let (level, pop) = if nw_id.is_leaf() {
+            debug_assert!(
+                children.iter().all(|c| c.is_leaf()),
+                "Attempting to create a node containing leaves & nodes!"
+            );
+            (1, children.iter().filter(|c| c.leaf_state() != Some(0)).count())
+        } else {
+            debug_assert!(
+                children.iter().all(|c| !c.is_leaf()),
+                "Attempting to create a node containing leaves & nodes!"
+            );
+            let level = nw_id.fetch_node(self).level + 1;
+            let pop = children.iter().map(|c| c.fetch_node(self).pop).sum();
+            (level, pop)
         };
-        self.get_id(QTree::Node(n))
+        let id = ID(self.nodes.len() as u32);
+        debug_assert!(!id.is_leaf(), "node arena exhausted");
+        self.nodes.push(Node {
+            north_west: nw_id,
+            north_east: ne_id,
+            south_west: sw_id,
+            south_east: se_id,
+            pop,
+            level,
+        });
+        self.index[slot] = id.0;
+        if self.nodes.len() * 2 > self.index.len() {
+            self.rebuild_index();
+        }
+        id
     }
 
     pub fn empty_tree(&mut self, level: usize) -> ID {
@@ -163,68 +362,67 @@ impl Space {
         }
     }
 
-    pub fn set_tree_pos(&mut self, tree: ID, x: usize, y: usize, pop2: usize) -> ID {
-        match *tree.fetch_from(self) {
-            QTree::Leaf(_) => self.new_leaf(pop2),
-            QTree::Node(Node {
-                level,
-                pop: _,
-                next: _,
-                north_west,
-                north_east,
-                south_west,
-                south_east,
-            }) => {
-                let dim = 2usize.pow(level as u32 - 1);
-                if x < dim && y < dim {
-                    let new_sw = self.set_tree_pos(south_west, x, y, pop2);
-                    self.new_node(north_west, north_east, new_sw, south_east)
-                } else if x < dim && y >= dim {
-                    let new_nw = self.set_tree_pos(north_west, x, y - dim, pop2);
-                    self.new_node(new_nw, north_east, south_west, south_east)
-                } else if x >= dim && y < dim {
-                    let new_se = self.set_tree_pos(south_east, x - dim, y, pop2);
-                    self.new_node(north_west, north_east, south_west, new_se)
-                } else if x >= dim && y >= dim {
-                    let new_ne = self.set_tree_pos(north_east, x - dim, y - dim, pop2);
-                    self.new_node(north_west, new_ne, south_west, south_east)
-                } else {
-                    ID(666)
-                }
-            }
+    /// A tree with every cell in `state`; like an empty tree it has one node per level.
+    pub fn filled_tree(&mut self, level: usize, state: usize) -> ID {
+        if level == 0 {
+            self.new_leaf(state)
+        } else {
+            let c = self.filled_tree(level - 1, state);
+            self.new_node(c, c, c, c)
         }
     }
 
-    fn get_tree_cell(&self, tree_id: ID, x: usize, y: usize) -> usize {
-        match *tree_id.fetch_from(self) {
-            QTree::Leaf(c) => c.0,
-            QTree::Node(Node {
-                level,
-                pop: _,
-                next: _,
-                north_west,
-                north_east,
-                south_west,
-                south_east,
-            }) => {
-                let dim = 2usize.pow(level as u32 - 1);
-                if x < dim && y < dim {
-                    self.get_tree_cell(south_west, x, y)
-                } else if x < dim && y >= dim {
-                    self.get_tree_cell(north_west, x, y - dim)
-                } else if x >= dim && y < dim {
-                    self.get_tree_cell(south_east, x - dim, y)
-                } else if x >= dim && y >= dim {
-                    self.get_tree_cell(north_east, x - dim, y - dim)
-                } else {
-                    666
-                }
-            }
+    pub fn set_tree_pos(&mut self, tree: ID, x: u64, y: u64, pop2: usize) -> ID {
+        if tree.is_leaf() {
+            return self.new_leaf(pop2);
+        }
+        let Node {
+            level,
+            north_west,
+            north_east,
+            south_west,
+            south_east,
+            ..
+        } = *tree.fetch_node(self);
+        let dim = 1u64 << (level - 1);
+        if x < dim && y < dim {
+            let new_sw = self.set_tree_pos(south_west, x, y, pop2);
+            self.new_node(north_west, north_east, new_sw, south_east)
+        } else if x < dim {
+            let new_nw = self.set_tree_pos(north_west, x, y - dim, pop2);
+            self.new_node(new_nw, north_east, south_west, south_east)
+        } else if y < dim {
+            let new_se = self.set_tree_pos(south_east, x - dim, y, pop2);
+            self.new_node(north_west, north_east, south_west, new_se)
+        } else {
+            let new_ne = self.set_tree_pos(north_east, x - dim, y - dim, pop2);
+            self.new_node(north_west, new_ne, south_west, south_east)
+        }
+    }
+
+    /// State of the cell at (x, y) relative to the tree's lower corner.
+    pub fn get_tree_cell(&self, tree_id: ID, x: u64, y: u64) -> usize {
+        if let Some(state) = tree_id.leaf_state() {
+            return state;
+        }
+        let node = tree_id.fetch_node(self);
+        if node.pop == 0 {
+            return 0;
+        }
+        let dim = 1u64 << (node.level - 1);
+        if x < dim && y < dim {
+            self.get_tree_cell(node.south_west, x, y)
+        } else if x < dim {
+            self.get_tree_cell(node.north_west, x, y - dim)
+        } else if y < dim {
+            self.get_tree_cell(node.south_east, x - dim, y)
+        } else {
+            self.get_tree_cell(node.north_east, x - dim, y - dim)
         }
     }
 
     pub fn expand_tree(&mut self, tree_id: ID) -> ID {
-        let level = tree_id.fetch_node(self).level;
+        let level = tree_id.fetch_node(self).level as usize;
         let border = self.empty_tree(level - 1);
         let (root_nw, root_ne, root_sw, root_se) = {
             let root = tree_id.fetch_node(self);
@@ -244,99 +442,289 @@ impl Space {
         self.new_node(nw, ne, sw, se)
     }
 
-    pub fn get_coords_level(&self, tree_id: ID, xoffset: i32, yoffset: i32, xstart: i32, ystart: i32, xend: i32, yend: i32, target_level: usize) -> Vec<(i32, i32, usize)> {
-        match tree_id.fetch_from(&self) {
-            QTree::Node(Node {
-                pop,
-                level,
-                next: _,
-                north_west,
-                north_east,
-                south_west,
-                south_east,
-            }) => {
-                if target_level == *level {
-                    vec![(xoffset, yoffset, *pop)]
-                }  else if *pop > 0 {
-                    let dim = 2i32.pow(*level as u32 - 1);
+    /// Shrink a tree to the smallest subtree of at least `min_level` still holding all of its
+    /// population, returning the subtree and its offset from the tree's lower corner.
+    ///
+    /// Each round halves the tree by picking one of the nine half-size squares aligned to a
+    /// quarter of its width, the centred one first, so the result can follow a pattern that has
+    /// drifted away from the middle.
+    pub fn contract(&mut self, tree_id: ID, min_level: usize) -> (ID, u64, u64) {
+        let (mut tree, mut x, mut y) = (tree_id, 0, 0);
+        'shrink: while self.level(tree) > min_level {
+            let node = *tree.fetch_node(self);
+            let quarter = 1u64 << (node.level - 2);
+            // Grandchildren indexed [row][column], rows from south to north.
+            let [nw, ne, sw, se] = node.children().map(|c| *c.fetch_node(self));
+            let grid = [
+                [sw.south_west, sw.south_east, se.south_west, se.south_east],
+                [sw.north_west, sw.north_east, se.north_west, se.north_east],
+                [nw.south_west, nw.south_east, ne.south_west, ne.south_east],
+                [nw.north_west, nw.north_east, ne.north_west, ne.north_east],
+            ];
+            let candidates = [(1, 1), (0, 0), (1, 0), (2, 0), (0, 1), (2, 1), (0, 2), (1, 2), (2, 2)];
+            for (col, row) in candidates {
+                let pop: usize = (row..row + 2)
+                    .flat_map(|r| (col..col + 2).map(move |c| (r, c)))
+                    .map(|(r, c)| self.population(grid[r][c]))
+                    .sum();
+                if pop == node.pop {
+                    tree = self.new_node(
+                        grid[row + 1][col],
+                        grid[row + 1][col + 1],
+                        grid[row][col],
+                        grid[row][col + 1],
+                    );
+                    x += col as u64 * quarter;
+                    y += row as u64 * quarter;
+                    continue 'shrink;
+                }
+            }
+            break;
+        }
+        (tree, x, y)
+    }
+
+    /// The level-`level` square whose lower corner sits at (x, y) relative to the lower corner
+    /// of `tree_id`; cells outside the tree are dead.
+    ///
+    /// Aligned squares are existing nodes and are returned as they are. Otherwise the window is
+    /// split into quadrants until each lies inside a single child, so the work is proportional to
+    /// the non-empty part of the window.
+    pub fn window(&mut self, tree_id: ID, x: i64, y: i64, level: usize) -> ID {
+        self.window_memo(tree_id, x, y, level, &mut HashMap::new())
+    }
+
+    fn window_memo(
+        &mut self,
+        tree_id: ID,
+        x: i64,
+        y: i64,
+        level: usize,
+        memo: &mut HashMap<(ID, i64, i64, usize), ID>,
+    ) -> ID {
+        let tree_level = self.level(tree_id);
+        let (tree_dim, dim) = (1i64 << tree_level, 1i64 << level);
+        if self.population(tree_id) == 0 || x >= tree_dim || y >= tree_dim || x + dim <= 0 || y + dim <= 0 {
+            return self.empty_tree(level);
+        }
+        if x == 0 && y == 0 && level == tree_level {
+            return tree_id;
+        }
+        if let Some(&id) = memo.get(&(tree_id, x, y, level)) {
+            return id;
+        }
+        let half = tree_dim / 2;
+        let (east, north) = (x >= half, y >= half);
+        let result = if level == 0 {
+            self.new_leaf(self.get_tree_cell(tree_id, x as u64, y as u64))
+        } else if level < tree_level && east == (x + dim > half) && north == (y + dim > half) {
+            let node = tree_id.fetch_node(self);
+            let child = match (east, north) {
+                (false, false) => node.south_west,
+                (true, false) => node.south_east,
+                (false, true) => node.north_west,
+                (true, true) => node.north_east,
+            };
+            let (cx, cy) = (if east { x - half } else { x }, if north { y - half } else { y });
+            self.window_memo(child, cx, cy, level, memo)
+        } else {
+            let h = dim / 2;
+            let sw = self.window_memo(tree_id, x, y, level - 1, memo);
+            let se = self.window_memo(tree_id, x + h, y, level - 1, memo);
+            let nw = self.window_memo(tree_id, x, y + h, level - 1, memo);
+            let ne = self.window_memo(tree_id, x + h, y + h, level - 1, memo);
+            self.new_node(nw, ne, sw, se)
+        };
+        memo.insert((tree_id, x, y, level), result);
+        result
+    }
+
+    /// `tree_id` rotated or reflected about its centre.
+    ///
+    /// Only the children of each node are rearranged, and each distinct node is transformed
+    /// once, so the cost follows the number of distinct nodes rather than the number of cells.
+    pub fn transform(&mut self, tree_id: ID, transform: Transform) -> ID {
+        self.transform_memo(tree_id, transform, &mut HashMap::new())
+    }
+
+    fn transform_memo(&mut self, tree_id: ID, transform: Transform, memo: &mut HashMap<ID, ID>) -> ID {
+        // Empty trees look the same in every orientation.
+        if tree_id.is_leaf() || self.population(tree_id) == 0 {
+            return tree_id;
+        }
+        if let Some(&id) = memo.get(&tree_id) {
+            return id;
+        }
+        let node = *tree_id.fetch_node(self);
+        // Children indexed [row][column], rows from south to north.
+        let grid = [[node.south_west, node.south_east], [node.north_west, node.north_east]];
+        let mut moved = grid;
+        for (row, children) in grid.iter().enumerate() {
+            for (col, &child) in children.iter().enumerate() {
+                let (c, r) = transform.apply(col as i64, row as i64, 2, 2);
+                moved[r as usize][c as usize] = self.transform_memo(child, transform, memo);
+            }
+        }
+        let result = self.new_node(moved[1][0], moved[1][1], moved[0][0], moved[0][1]);
+        memo.insert(tree_id, result);
+        result
+    }
+
+    /// `tree_id` with the cells outside the inclusive rectangle `(x0, y0, x1, y1)`, relative to
+    /// its lower corner, cleared, or with `keep_inside` false the cells inside it.
+    ///
+    /// Nodes entirely on one side of the rectangle's edge are kept or dropped whole, so only
+    /// the non-empty nodes straddling the edge are rebuilt.
+    pub fn clip(&mut self, tree_id: ID, rect: (i64, i64, i64, i64), keep_inside: bool) -> ID {
+        if self.population(tree_id) == 0 {
+            return tree_id;
+        }
+        let (x0, y0, x1, y1) = rect;
+        let level = self.level(tree_id);
+        let dim = 1i64 << level;
+        let inside = x0 <= 0 && y0 <= 0 && x1 >= dim - 1 && y1 >= dim - 1;
+        let outside = x1 < 0 || y1 < 0 || x0 >= dim || y0 >= dim;
+        if inside || outside {
+            return if inside == keep_inside { tree_id } else { self.empty_tree(level) };
+        }
+        let node = *tree_id.fetch_node(self);
+        let half = dim / 2;
+        let shifted = |dx: i64, dy: i64| (x0 - dx, y0 - dy, x1 - dx, y1 - dy);
+        let nw = self.clip(node.north_west, shifted(0, half), keep_inside);
+        let ne = self.clip(node.north_east, shifted(half, half), keep_inside);
+        let sw = self.clip(node.south_west, shifted(0, 0), keep_inside);
+        let se = self.clip(node.south_east, shifted(half, 0), keep_inside);
+        self.new_node(nw, ne, sw, se)
+    }
+
+    /// Combine two trees of the same level cell by cell.
+    ///
+    /// Identical and empty subtrees are settled without descending into them, and each pair
+    /// of distinct nodes is combined once.
+    pub fn combine(&mut self, a: ID, b: ID, op: SetOp) -> ID {
+        self.combine_memo(a, b, op, &mut HashMap::new())
+    }
+
+    fn combine_memo(&mut self, a: ID, b: ID, op: SetOp, memo: &mut HashMap<(ID, ID), ID>) -> ID {
+        if let (Some(state_a), Some(state_b)) = (a.leaf_state(), b.leaf_state()) {
+            return self.new_leaf(op.apply(state_a, state_b));
+        }
+        let (empty_a, empty_b) = (self.population(a) == 0, self.population(b) == 0);
+        let shortcut = match op {
+            SetOp::Union | SetOp::Xor if empty_a => Some(b),
+            SetOp::Union | SetOp::Xor | SetOp::Subtract if empty_b => Some(a),
+            SetOp::Intersect | SetOp::Subtract if empty_a => Some(a),
+            SetOp::Intersect if empty_b => Some(b),
+            SetOp::Union | SetOp::Intersect if a == b => Some(a),
+            SetOp::Subtract | SetOp::Xor if a == b => Some(self.empty_tree(self.level(a))),
+            _ => None,
+        };
+        if let Some(id) = shortcut {
+            return id;
+        }
+        if let Some(&id) = memo.get(&(a, b)) {
+            return id;
+        }
+        let (na, nb) = (*a.fetch_node(self), *b.fetch_node(self));
+        let nw = self.combine_memo(na.north_west, nb.north_west, op, memo);
+        let ne = self.combine_memo(na.north_east, nb.north_east, op, memo);
+        let sw = self.combine_memo(na.south_west, nb.south_west, op, memo);
+        let se = self.combine_memo(na.south_east, nb.south_east, op, memo);
+        let result = self.new_node(nw, ne, sw, se);
+        memo.insert((a, b), result);
+        result
+    }
+
+    /// A copy of the tree `tree_id` of `other`, interned in this space.
+    pub fn import(&mut self, other: &Space, tree_id: ID) -> ID {
+        self.import_memo(other, tree_id, &mut HashMap::new())
+    }
+
+    fn import_memo(&mut self, other: &Space, tree_id: ID, memo: &mut HashMap<ID, ID>) -> ID {
+        if tree_id.is_leaf() {
+            return tree_id;
+        }
+        if let Some(&id) = memo.get(&tree_id) {
+            return id;
+        }
+        let node = tree_id.fetch_node(other);
+        let [nw, ne, sw, se] = node.children().map(|c| self.import_memo(other, c, memo));
+        let result = self.new_node(nw, ne, sw, se);
+        memo.insert(tree_id, result);
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_coords_level(&self, tree_id: ID, xoffset: i64, yoffset: i64, xstart: i64, ystart: i64, xend: i64, yend: i64, target_level: usize) -> Vec<(i64, i64, usize)> {
+        match tree_id.leaf_state() {
+            Some(0) => vec![],
+            Some(_) => vec![(xoffset, yoffset, 1)],
+            None => {
+                let Node {
+                    pop,
+                    level,
+                    north_west,
+                    north_east,
+                    south_west,
+                    south_east,
+                    ..
+                } = *tree_id.fetch_node(self);
+                if target_level == level as usize {
+                    vec![(xoffset, yoffset, pop)]
+                }  else if pop > 0 {
+                    let dim = 1i64 << (level - 1);
                     let mut results = vec![];
                     if xoffset > xend || yoffset > yend || xoffset + dim*2 < xstart - 1 || yoffset + dim*2 < ystart - 1 {
                         return vec![];
                     }
 
-                    results.append(&mut self.get_coords_level(*north_west, xoffset, yoffset + dim, xstart, ystart, xend, yend, target_level));
-                    results.append(&mut self.get_coords_level(*north_east, xoffset + dim, yoffset + dim, xstart, ystart, xend, yend, target_level));
-                    results.append(&mut self.get_coords_level(*south_west, xoffset, yoffset, xstart, ystart, xend, yend, target_level));
-                    results.append(&mut self.get_coords_level(*south_east, xoffset + dim, yoffset, xstart, ystart, xend, yend, target_level));
+                    results.append(&mut self.get_coords_level(north_west, xoffset, yoffset + dim, xstart, ystart, xend, yend, target_level));
+                    results.append(&mut self.get_coords_level(north_east, xoffset + dim, yoffset + dim, xstart, ystart, xend, yend, target_level));
+                    results.append(&mut self.get_coords_level(south_west, xoffset, yoffset, xstart, ystart, xend, yend, target_level));
+                    results.append(&mut self.get_coords_level(south_east, xoffset + dim, yoffset, xstart, ystart, xend, yend, target_level));
                     results
                 } else {
                     vec![]
                 }
             }
-            QTree::Leaf(Leaf(0)) => vec![],
-            QTree::Leaf(_) => vec![(xoffset, yoffset, 1)],
         }
     }
 
 
-    pub fn get_coords(&self, tree_id: ID, xoffset: i32, yoffset: i32, xstart: i32, ystart: i32, xend: i32, yend: i32) -> Vec<(i32, i32)> {
-        match tree_id.fetch_from(&self) {
-            QTree::Node(Node {
-                pop,
-                level,
-                next: _,
-                north_west,
-                north_east,
-                south_west,
-                south_east,
-            }) => {
-                if *pop > 0 {
-                    let dim = 2i32.pow(*level as u32 - 1);
+    #[allow(clippy::too_many_arguments)]
+    pub fn get_coords(&self, tree_id: ID, xoffset: i64, yoffset: i64, xstart: i64, ystart: i64, xend: i64, yend: i64) -> Vec<(i64, i64, usize)> {
+        match tree_id.leaf_state() {
+            Some(0) => vec![],
+            Some(state) => vec![(xoffset, yoffset, state)],
+            None => {
+                let Node {
+                    pop,
+                    level,
+                    north_west,
+                    north_east,
+                    south_west,
+                    south_east,
+                    ..
+                } = *tree_id.fetch_node(self);
+                if pop > 0 {
+                    let dim = 1i64 << (level - 1);
                     let mut results = vec![];
                     if xoffset > xend || yoffset > yend || xoffset + dim*2 < xstart - 1 || yoffset + dim*2 < ystart - 1 {
                         return vec![];
                     }
 
-                    results.append(&mut self.get_coords(*north_west, xoffset, yoffset + dim, xstart, ystart, xend, yend));
-                    results.append(&mut self.get_coords(*north_east, xoffset + dim, yoffset + dim, xstart, ystart, xend, yend));
-                    results.append(&mut self.get_coords(*south_west, xoffset, yoffset, xstart, ystart, xend, yend));
-                    results.append(&mut self.get_coords(*south_east, xoffset + dim, yoffset, xstart, ystart, xend, yend));
+                    results.append(&mut self.get_coords(north_west, xoffset, yoffset + dim, xstart, ystart, xend, yend));
+                    results.append(&mut self.get_coords(north_east, xoffset + dim, yoffset + dim, xstart, ystart, xend, yend));
+                    results.append(&mut self.get_coords(south_west, xoffset, yoffset, xstart, ystart, xend, yend));
+                    results.append(&mut self.get_coords(south_east, xoffset + dim, yoffset, xstart, ystart, xend, yend));
                     results
                 } else {
                     vec![]
                 }
             }
-            QTree::Leaf(Leaf(0)) => vec![],
-            QTree::Leaf(_) => vec![(xoffset, yoffset)],
         }
     }
 
-    fn centered_horizontal(&mut self, west: ID, east: ID) -> ID {
-        let (west, east) = (west.fetch_node(self), east.fetch_node(self));
-        debug_assert!(west.level == east.level, "levels must be the same");
-
-        let (nw, ne, sw, se) = (
-            west.north_east.fetch_node(self).south_east,
-            east.north_west.fetch_node(self).south_west,
-            west.south_east.fetch_node(self).north_east,
-            east.south_west.fetch_node(self).north_west,
-        );
-        self.new_node(nw, ne, sw, se)
-    }
-
-    fn centered_vertical(&mut self, north: ID, south: ID) -> ID {
-        let (north, south) = (north.fetch_node(self), south.fetch_node(self));
-        debug_assert!(north.level == south.level, "levels must be the same");
-
-        let (nw, ne, sw, se) = (
-            north.south_west.fetch_node(self).south_east,
-            north.south_east.fetch_node(self).south_west,
-            south.north_west.fetch_node(self).north_east,
-            south.north_east.fetch_node(self).north_west,
-        );
-        self.new_node(nw, ne, sw, se)
-    }
-
     fn centered_sub(&mut self, id: ID) -> ID {
         let node = id.fetch_node(self);
         let (nw, ne, sw, se) = (
@@ -348,52 +736,25 @@ impl Space {
         self.new_node(nw, ne, sw, se)
     }
 
-    fn centered_subsub(&mut self, node: ID) -> ID {
-        let node = node.fetch_node(self);
-        let (nw, ne, sw, se) = (
-            node.north_west
-                .fetch_node(self)
-                .south_east
-                .fetch_node(self)
-                .south_east,
-            node.north_east
-                .fetch_node(self)
-                .south_west
-                .fetch_node(self)
-                .south_west,
-            node.south_west
-                .fetch_node(self)
-                .north_east
-                .fetch_node(self)
-                .north_east,
-            node.south_east
-                .fetch_node(self)
-                .north_west
-                .fetch_node(self)
-                .north_west,
-        );
-        self.new_node(nw, ne, sw, se)
-    }
-
-    fn horizontalForward(&mut self, node_w: ID, node_e: ID, j: usize) -> ID {
+    fn horizontal_forward(&mut self, node_w: ID, node_e: ID, j: usize) -> ID {
         let x = self.new_node(
             node_w.fetch_node(self).north_east,
             node_e.fetch_node(self).north_west,
             node_w.fetch_node(self).south_east,
             node_e.fetch_node(self).south_west,
         );
-        return self.evolve_tree(x, j)
+        self.evolve_tree(x, j)
     }
-    fn verticalForward(&mut self, node_n: ID, node_s: ID, j: usize) -> ID {
+    fn vertical_forward(&mut self, node_n: ID, node_s: ID, j: usize) -> ID {
         let x = self.new_node(
             node_n.fetch_node(self).south_west,
             node_n.fetch_node(self).south_east,
             node_s.fetch_node(self).north_west,
             node_s.fetch_node(self).north_east,
         );
-        return self.evolve_tree(x, j)
+        self.evolve_tree(x, j)
     }
-    fn centeredForward(&mut self, node: ID, j: usize) -> ID {
+    fn centered_forward(&mut self, node: ID, j: usize) -> ID {
         let n = node.fetch_node(self);
         let x = self.new_node(
             n.north_west.fetch_node(self).south_east,
@@ -401,51 +762,40 @@ impl Space {
             n.south_west.fetch_node(self).north_east,
             n.south_east.fetch_node(self).north_west,
         );
-        return self.evolve_tree(x, j)
+        self.evolve_tree(x, j)
     }
 
+    /// Advance `tree_id` by `2^min(j, level - 2)` generations, returning its centred half.
     pub fn evolve_tree(&mut self, tree_id: ID, j: usize) -> ID {
-        {
-            let inode = tree_id.fetch_node(self);
-            debug_assert!(inode.level >= 2, "must be level 2 or higher");
-        }
-
-        if let Some(next) = tree_id.fetch_node(self).next {
-            return next;
+        let n = tree_id.fetch_node(self);
+        debug_assert!(n.level >= 2, "must be level 2 or higher");
+        let curr_level = n.level as usize;
+        let next_j = cmp::min(j, curr_level - 2);
+        let key = (tree_id, next_j as u8);
+
+        if let Some(&result) = self.results.get(&key) {
+            return result;
         }
 
-        if tree_id.fetch_node(self).level == 2 {
-            self.evolve4x4(tree_id)
+        if curr_level == 2 {
+            let result = self.evolve4x4(tree_id);
+            self.results.insert(key, result);
+            result
         } else {
-
-            let n = tree_id.fetch_node(self);
-            let curr_level = n.level;
-            let next_j = cmp::min(j, n.level - 2);
-
             let (tree_nw, tree_ne, tree_sw, tree_se) = {
                 (n.north_west, n.north_east, n.south_west, n.south_east)
             };
             
             let n00 = self.evolve_tree(tree_nw, next_j);
-            let n01 = self.horizontalForward(tree_nw, tree_ne, next_j);
+            let n01 = self.horizontal_forward(tree_nw, tree_ne, next_j);
             let n02 = self.evolve_tree(tree_ne, next_j);
-            let n10 = self.verticalForward(tree_nw, tree_sw, next_j);
-            let n11 = self.centeredForward(tree_id, next_j);
-            let n12 = self.verticalForward(tree_ne, tree_se, next_j);
+            let n10 = self.vertical_forward(tree_nw, tree_sw, next_j);
+            let n11 = self.centered_forward(tree_id, next_j);
+            let n12 = self.vertical_forward(tree_ne, tree_se, next_j);
             let n20 = self.evolve_tree(tree_sw, next_j);
-            let n21 = self.horizontalForward(tree_sw, tree_se, next_j);
+            let n21 = self.horizontal_forward(tree_sw, tree_se, next_j);
             let n22 = self.evolve_tree(tree_se, next_j);
 
-            // let n00 = self.centered_sub(tree_nw);
-            // let n01 = self.centered_horizontal(tree_nw, tree_ne);
-            // let n02 = self.centered_sub(tree_ne);
-            // let n10 = self.centered_vertical(tree_nw, tree_sw);
-            // let n11 = self.centered_subsub(tree_id);
-            // let n12 = self.centered_vertical(tree_ne, tree_se);
-            // let n20 = self.centered_sub(tree_sw);
-            // let n21 = self.centered_horizontal(tree_sw, tree_se);
-            // let n22 = self.centered_sub(tree_se);
-
             let (nw, ne, sw, se) = {
                 if j < curr_level - 2 {
                     let nw = self.new_node(n00, n01, n10, n11);
@@ -474,39 +824,7 @@ impl Space {
 
             let result = self.new_node(nw, ne, sw, se);
 
-            let (xid, x) = self.table.remove_by_left(&tree_id).unwrap();
-
-            match x {
-                QTree::Node(Node {
-                    pop,
-                    level,
-                    next,
-                    north_west,
-                    north_east,
-                    south_west,
-                    south_east,
-                }) => {
-                    let new_node = QTree::Node(Node {
-                        level: level,
-                        pop: pop,
-                        next: Some(result),
-                        north_west: north_west,
-                        north_east: north_east,
-                        south_west: south_west,
-                        south_east: south_east,
-                    });
-                    match self.table.get_by_right(&new_node) {
-                        Some(id) => {
-                            return *id;
-                        },
-                        _ => {
-                            self.table.insert(tree_id, new_node);
-                        }
-                    }
-                }
-                QTree::Leaf(level) => unimplemented!("Need to copy leaf correctly.")
-            };
-
+            self.results.insert(key, result);
             result
         }
     }
@@ -517,33 +835,125 @@ impl Space {
             inode.level == 2,
             "manual evolution only at level 2 possible"
         );
+        // `bits` holds the live (state 1) cells, `states` every cell, both in row-major order.
         let mut bits: u16 = 0;
+        let mut states = [0usize; 16];
         for y in 0..4 {
             for x in 0..4 {
-                bits = (bits << 1) + self.get_tree_cell(node_id, x, y) as u16;
+                let state = self.get_tree_cell(node_id, x as u64, y as u64);
+                states[y * 4 + x] = state;
+                bits = (bits << 1) + (state == 1) as u16;
             }
         }
         let (nw, ne, sw, se) = (
-            self.life(bits >> 5),
-            self.life(bits >> 4),
-            self.life(bits >> 1),
-            self.life(bits),
+            self.life(bits >> 5, states[5]),
+            self.life(bits >> 4, states[6]),
+            self.life(bits >> 1, states[9]),
+            self.life(bits, states[10]),
         );
-        let result = self.new_node(sw, se, nw, ne);
-        result
+        self.new_node(sw, se, nw, ne)
     }
 
-    fn life(&mut self, mut bitmask: u16) -> ID {
-        if bitmask == 0 {
+    fn life(&mut self, mut bitmask: u16, state: usize) -> ID {
+        if bitmask == 0 && state == 0 {
             return self.new_leaf(0);
         }
-        let center = (bitmask >> 5) & 1;
-        bitmask &= 0b00000__111_0101_0111;
+        bitmask &= 0b0000_0111_0101_0111;
         let ncount = bitmask.count_ones();
-        if ncount == 3 || (ncount == 2 && center != 0) {
-            self.new_leaf(1)
-        } else {
-            self.new_leaf(0)
+        self.new_leaf(self.rule.next_state(state, ncount))
+    }
+
+    /// Smallest rectangle holding every non-dead cell of `tree_id`, as inclusive
+    /// `(min_x, min_y, max_x, max_y)` relative to its lower corner, or `None` if it is empty.
+    ///
+    /// Each edge is found by descending only into the non-empty children nearest to it.
+    pub fn bounding_box(&self, tree_id: ID) -> Option<(u64, u64, u64, u64)> {
+        if self.population(tree_id) == 0 {
+            return None;
+        }
+        let mut memo = HashMap::new();
+        Some((
+            self.edge(tree_id, Edge::West, &mut memo),
+            self.edge(tree_id, Edge::South, &mut memo),
+            self.edge(tree_id, Edge::East, &mut memo),
+            self.edge(tree_id, Edge::North, &mut memo),
+        ))
+    }
+
+    /// Coordinate of the outermost non-dead cell of a non-empty tree towards `edge`.
+    fn edge(&self, id: ID, edge: Edge, memo: &mut HashMap<(ID, Edge), u64>) -> u64 {
+        if id.is_leaf() {
+            return 0;
+        }
+        if let Some(&offset) = memo.get(&(id, edge)) {
+            return offset;
+        }
+        let node = id.fetch_node(self);
+        let dim = 1u64 << (node.level - 1);
+        let (west, east) = ([node.north_west, node.south_west], [node.north_east, node.south_east]);
+        let (south, north) = ([node.south_west, node.south_east], [node.north_west, node.north_east]);
+        // The half nearest the edge wins whenever it has any cells; offsets are of each half.
+        let (near, near_offset, far, far_offset) = match edge {
+            Edge::West => (west, 0, east, dim),
+            Edge::East => (east, dim, west, 0),
+            Edge::South => (south, 0, north, dim),
+            Edge::North => (north, dim, south, 0),
+        };
+        let pick = |a: Option<u64>, b: Option<u64>| match (a, b, edge) {
+            (Some(a), Some(b), Edge::West | Edge::South) => Some(a.min(b)),
+            (Some(a), Some(b), Edge::East | Edge::North) => Some(a.max(b)),
+            (a, b, _) => a.or(b),
+        };
+        let mut search = |half: [ID; 2], offset: u64| {
+            let [a, b] = half.map(|c| {
+                if self.population(c) > 0 {
+                    Some(self.edge(c, edge, memo))
+                } else {
+                    None
+                }
+            });
+            pick(a, b).map(|o| o + offset)
+        };
+        let offset = search(near, near_offset)
+            .or_else(|| search(far, far_offset))
+            .expect("edge of an empty tree");
+        memo.insert((id, edge), offset);
+        offset
+    }
+
+    /// Count the cells of `tree_id` in each state, indexed by state. Dead cells are not counted,
+    /// so entry 0 is always zero.
+    ///
+    /// The result has at least one entry per state of the current rule, more if the tree holds
+    /// cells written with a higher state.
+    pub fn population_by_state(&self, tree_id: ID) -> Vec<usize> {
+        fn count(sp: &Space, id: ID, memo: &mut HashMap<ID, Vec<usize>>) -> Vec<usize> {
+            if let Some(state) = id.leaf_state() {
+                let mut counts = vec![0; state + 1];
+                if state > 0 {
+                    counts[state] = 1;
+                }
+                return counts;
+            }
+            if let Some(counts) = memo.get(&id) {
+                return counts.clone();
+            }
+            let mut counts = vec![];
+            for child in id.fetch_node(sp).children().iter() {
+                for (state, c) in count(sp, *child, memo).into_iter().enumerate() {
+                    if state >= counts.len() {
+                        counts.resize(state + 1, 0);
+                    }
+                    counts[state] += c;
+                }
+            }
+            memo.insert(id, counts.clone());
+            counts
+        }
+        let mut counts = count(self, tree_id, &mut HashMap::new());
+        if counts.len() < self.rule.states() {
+            counts.resize(self.rule.states(), 0);
         }
+        counts
     }
-}
\ No newline at end of file
+}
@@ -1,9 +1,29 @@
+mod analysis;
+mod cells;
+mod census;
 mod gol;
+mod macrocell;
+mod pattern;
+mod rle;
+mod rule;
+mod search;
+mod soup;
+mod transform;
 mod utils;
+mod view;
+
+pub use analysis::{PeriodKind, Periodicity};
+pub use census::{Census, CensusObject};
+pub use pattern::{PasteMode, Pattern};
+pub use rle::{RleError, RleLimits};
+pub use search::{Search, SearchMatch};
+pub use soup::Symmetry;
+pub use transform::Transform;
+pub use view::{BoundingBox, View};
 
 // #![allow(dead_code)]
 use js_sys::Array;
-use regex::Regex;
+use std::collections::{HashMap, VecDeque};
 use wasm_bindgen::prelude::*;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -12,268 +32,1036 @@ use wasm_bindgen::prelude::*;
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+/// Largest power-of-two step evolved in one go; longer advances repeat steps of this size.
+const MAX_STEP_POW: usize = 48;
+
+/// Level the root never contracts below.
+const MIN_ROOT_LEVEL: usize = 3;
+
+/// Number of undoable changes kept unless `set_undo_depth` says otherwise.
+pub const DEFAULT_UNDO_DEPTH: usize = 100;
+
 /// # Universe API
 /// Provides an interface for working with Gosper's Algorithm using absolute coordinates.
 
 #[wasm_bindgen]
 pub struct Universe {
-    rootX: i32,
-    rootY: i32,
+    root_x: i64,
+    root_y: i64,
     space: gol::Space,
     root_id: gol::ID,
+    snapshots: Vec<Snapshot>,
+    undo_stack: VecDeque<Snapshot>,
+    redo_stack: Vec<Snapshot>,
+    undo_depth: usize,
+    /// The state right after the last load, for `reset_to_start`.
+    start: Snapshot,
+    comments: Vec<String>,
+    rle_limits: RleLimits,
+    generation: u128,
+}
+
+/// A pinned universe state; its root is kept alive across node garbage collection.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Snapshot {
+    root_id: gol::ID,
+    root_x: i64,
+    root_y: i64,
+    generation: u128,
 }
 
 #[wasm_bindgen]
 impl Universe {
     /// Universe constructor
-    pub fn new(rx: i32, ry: i32) -> Universe {
+    pub fn new(rx: f64, ry: f64) -> Universe {
+        utils::set_panic_hook();
         let mut space = gol::Space::new();
-        let root_id = space.empty_tree(3);
+        let root_id = space.empty_tree(MIN_ROOT_LEVEL);
+        let start = Snapshot {
+            root_id,
+            root_x: cell_coord(rx),
+            root_y: cell_coord(ry),
+            generation: 0,
+        };
         Universe {
-            rootX: rx,
-            rootY: ry,
-            space: space,
-            root_id: root_id,
+            root_x: start.root_x,
+            root_y: start.root_y,
+            space,
+            root_id,
+            snapshots: vec![],
+            undo_stack: VecDeque::new(),
+            redo_stack: vec![],
+            undo_depth: DEFAULT_UNDO_DEPTH,
+            start,
+            comments: vec![],
+            rle_limits: RleLimits::default(),
+            generation: 0,
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.space.population(self.root_id)
+    }
+
+    /// Number of cells in each state, indexed by state (entry 0 is always zero).
+    pub fn population_by_state(&self) -> Array {
+        self.state_populations()
+            .into_iter()
+            .map(|count| JsValue::from(count as f64))
+            .collect()
+    }
+
+    /// Replace the universe with an RLE pattern whose top-left corner is at (0, 0).
+    ///
+    /// A `rule =` header switches the universe to that rule; without one the universe goes back
+    /// to Life, as the format specifies. The pattern's comments replace any previously loaded
+    /// ones. Malformed input or input beyond the configured limits is rejected without touching
+    /// the universe.
+    pub fn from_rle(&mut self, rle_str: String) -> Result<(), RleError> {
+        let pattern = Pattern::parse_rle(&rle_str, &self.rle_limits)?;
+        self.load_pattern(&pattern, 0, 0);
+        Ok(())
+    }
+
+    /// Set the size limits applied by `from_rle`, e.g. before loading untrusted files.
+    pub fn set_rle_limits(&mut self, max_dimension: u32, max_cells: usize) {
+        self.rle_limits = RleLimits {
+            max_dimension,
+            max_cells,
+        };
+    }
+
+    /// Replace the universe with `pattern` at (x, y) and make that the new start.
+    fn load_pattern(&mut self, pattern: &Pattern, x: i64, y: i64) {
+        self.root_id = self.space.empty_tree(MIN_ROOT_LEVEL);
+        self.root_x = x;
+        self.root_y = y;
+        let rule = pattern.rule().and_then(|r| r.parse().ok());
+        self.space.set_rule(rule.unwrap_or_else(rule::Rule::life));
+        for &(cx, cy, state) in pattern.cells() {
+            self.set_cell(x + cx as i64, y + cy as i64, state);
+        }
+        self.comments = pattern.comments().to_vec();
+        self.generation = pattern.generation_count();
+        self.mark_start();
+    }
+
+    pub fn set(&mut self, x: f64, y: f64) {
+        self.record(|uni| uni.set_cell(cell_coord(x), cell_coord(y), 1));
+    }
+
+    /// Export the whole pattern as RLE, framed by the bounding box of its live cells.
+    pub fn to_rle(&self) -> String {
+        let bbox = match self.bounding_box() {
+            Some(bbox) => bbox,
+            None => return self.write_rle(&[], 0, 0),
+        };
+        let cells: Vec<_> = self
+            .live_cells()
+            .into_iter()
+            .map(|(x, y, state)| (x - bbox.min_x, y - bbox.min_y, state))
+            .collect();
+        self.write_rle(&cells, bbox.width(), bbox.height())
+    }
+
+    /// Every non-dead cell as absolute `(x, y, state)`.
+    fn live_cells(&self) -> Vec<(i64, i64, usize)> {
+        let root_dim = self.root_dim();
+        self.space.get_coords(
+            self.root_id,
+            self.root_x,
+            self.root_y,
+            self.root_x,
+            self.root_y,
+            self.root_x + root_dim - 1,
+            self.root_y + root_dim - 1,
+        )
+    }
+
+    /// Export the cells inside the inclusive rectangle (x0, y0)-(x1, y1) as RLE.
+    pub fn to_rle_rect(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> String {
+        let area = cell_rect(x0, y0, x1, y1);
+        self.write_rle(&self.cells_in_rect(&area), area.width(), area.height())
+    }
+
+    /// Live cells inside `area`, relative to its top-left corner.
+    fn cells_in_rect(&self, area: &BoundingBox) -> Vec<(i64, i64, usize)> {
+        let BoundingBox { min_x, min_y, max_x, max_y } = *area;
+        self.space
+            .get_coords(self.root_id, self.root_x, self.root_y, min_x, min_y, max_x, max_y)
+            .into_iter()
+            .filter(|&(x, y, _)| x >= min_x && x <= max_x && y >= min_y && y <= max_y)
+            .map(|(x, y, state)| (x - min_x, y - min_y, state))
+            .collect()
+    }
+
+    /// Replace the universe with a plaintext `.cells` pattern whose top-left corner is at
+    /// (x, y), switching the universe to Life.
+    ///
+    /// The pattern's `!` comment lines replace any previously loaded comments.
+    pub fn from_cells(&mut self, text: &str, x: f64, y: f64) -> Result<(), String> {
+        let pattern = Pattern::from_cells(text)?;
+        self.load_pattern(&pattern, cell_coord(x), cell_coord(y));
+        Ok(())
+    }
+
+    /// Export the cells inside the inclusive rectangle (x0, y0)-(x1, y1) as plaintext.
+    pub fn to_cells(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> String {
+        let area = cell_rect(x0, y0, x1, y1);
+        let cells: Vec<_> = self.cells_in_rect(&area).into_iter().map(|(x, y, _)| (x, y)).collect();
+        cells::write(&cells, area.width(), area.height(), &self.comments)
+    }
+
+    /// Replace the universe with a Macrocell (`.mc`) pattern, centred on the origin as in Golly.
+    pub fn from_macrocell(&mut self, text: &str) -> Result<(), String> {
+        let mc = macrocell::read(&mut self.space, text)?;
+        let rule = match mc.rule {
+            Some(rule) => rule.parse()?,
+            None => rule::Rule::life(),
+        };
+        self.space.set_rule(rule);
+        let mut root_id = mc.root;
+        while self.space.level(root_id) < MIN_ROOT_LEVEL {
+            root_id = self.space.expand_tree(root_id);
+        }
+        let half = 1i64 << (self.space.level(root_id) - 1);
+        self.root_id = root_id;
+        self.root_x = -half;
+        self.root_y = -half;
+        self.contract_root();
+        self.comments = mc.comments;
+        self.generation = mc.generation;
+        self.mark_start();
+        self.maybe_collect();
+        Ok(())
+    }
+
+    /// Export the universe as Macrocell, one line per distinct node of the current root.
+    ///
+    /// Macrocell has no origin field, so reading the result back centres the root on (0, 0).
+    pub fn to_macrocell(&self) -> String {
+        let rule = self.space.rule();
+        let multi_state = rule.states() > 2
+            || self.space.population_by_state(self.root_id).len() > 2;
+        macrocell::write(
+            &self.space,
+            self.root_id,
+            &rule.to_string(),
+            multi_state,
+            &self.comments,
+            self.generation,
+        )
+    }
+
+    fn write_rle(&self, cells: &[(i64, i64, usize)], width: i64, height: i64) -> String {
+        let rule = self.space.rule();
+        let multi_state = rule.states() > 2 || cells.iter().any(|c| c.2 > 1);
+        rle::write(
+            cells,
+            width,
+            height,
+            &rule.to_string(),
+            multi_state,
+            &self.comments,
+            self.generation,
+        )
+    }
+
+    /// Kill the cell at (x, y).
+    pub fn clear_cell(&mut self, x: f64, y: f64) {
+        self.record(|uni| uni.set_cell(cell_coord(x), cell_coord(y), 0));
+    }
+
+    /// Flip the cell at (x, y): any live or dying state becomes dead, a dead cell becomes alive.
+    pub fn toggle(&mut self, x: f64, y: f64) {
+        let state = if self.get(x, y) == 0 { 1 } else { 0 };
+        self.record(|uni| uni.set_cell(cell_coord(x), cell_coord(y), state));
+    }
+
+    /// Write an arbitrary cell state at (x, y); 0 clears the cell.
+    pub fn set_state(&mut self, x: f64, y: f64, state: usize) -> Result<(), String> {
+        if state >= rule::MAX_STATES {
+            return Err(format!("cell state {} is out of range", state));
+        }
+        self.record(|uni| uni.set_cell(cell_coord(x), cell_coord(y), state));
+        Ok(())
+    }
+
+    /// Rotate or reflect the whole pattern about the centre of its bounding box.
+    pub fn transform(&mut self, transform: Transform) {
+        let bbox = match self.bounding_box() {
+            Some(bbox) => bbox,
+            None => return,
+        };
+        self.record(|uni| {
+            let (x, y) = transform.corner(uni.relative(&bbox), uni.root_dim());
+            let dest = transform.rect(&bbox);
+            uni.root_id = uni.space.transform(uni.root_id, transform);
+            uni.root_x = dest.min_x - x;
+            uni.root_y = dest.min_y - y;
+        });
+    }
+
+    /// Move the whole pattern by (dx, dy).
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        self.record(|uni| {
+            uni.root_x += cell_coord(dx);
+            uni.root_y += cell_coord(dy);
+        });
+    }
+
+    /// Rotate or reflect the cells inside the inclusive rectangle (x0, y0)-(x1, y1) about its
+    /// centre. Cells already under the transformed rectangle are replaced.
+    pub fn transform_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, transform: Transform) {
+        let area = cell_rect(x0, y0, x1, y1);
+        self.record(|uni| uni.move_rect(&area, Some(transform), 0, 0));
+    }
+
+    /// Move the cells inside the inclusive rectangle (x0, y0)-(x1, y1) by (dx, dy). Cells
+    /// already under the destination are replaced.
+    pub fn translate_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, dx: f64, dy: f64) {
+        let area = cell_rect(x0, y0, x1, y1);
+        self.record(|uni| uni.move_rect(&area, None, cell_coord(dx), cell_coord(dy)));
+    }
+
+    /// Lift the cells of `area` out of the tree, optionally transform them about the area's
+    /// centre, shift them by (dx, dy) and write them over the destination.
+    fn move_rect(&mut self, area: &BoundingBox, transform: Option<Transform>, dx: i64, dy: i64) {
+        let (width, height) = (area.width(), area.height());
+        let (mut piece, level) = self.lift(area);
+        // Position of the area's cells within the piece, and the rectangle they end up in.
+        let (mut x, mut y, mut dest) = (0, 0, *area);
+        if let Some(transform) = transform {
+            (x, y) = transform.corner((0, 0, width - 1, height - 1), 1 << level);
+            dest = transform.rect(area);
+            piece = self.space.transform(piece, transform);
         }
+        let dest = dest.translated(dx, dy);
+
+        let source = self.relative(area);
+        self.root_id = self.space.clip(self.root_id, source, false);
+        let target = self.relative(&dest);
+        self.root_id = self.space.clip(self.root_id, target, false);
+        self.graft(piece, dest.min_x - x, dest.min_y - y, &dest, gol::SetOp::Union);
+    }
+
+    /// The cells of `area` as a tree of the returned level whose lower corner is the area's
+    /// top-left corner; everything outside the area is dead.
+    fn lift(&mut self, area: &BoundingBox) -> (gol::ID, usize) {
+        let (width, height) = (area.width(), area.height());
+        let level = level_for(width.max(height));
+        let window = self.space.window(
+            self.root_id,
+            area.min_x - self.root_x,
+            area.min_y - self.root_y,
+            level,
+        );
+        (self.space.clip(window, (0, 0, width - 1, height - 1), true), level)
+    }
+
+    /// The cells inside the inclusive rectangle (x0, y0)-(x1, y1) as a pattern of that size,
+    /// carrying the current rule.
+    pub fn copy_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) -> Pattern {
+        let area = cell_rect(x0, y0, x1, y1);
+        let (width, height) = (area.width(), area.height());
+        let (piece, _) = self.lift(&area);
+        let cells = self
+            .space
+            .get_coords(piece, 0, 0, 0, 0, width - 1, height - 1)
+            .into_iter()
+            .map(|(x, y, state)| (x as i32, y as i32, state))
+            .collect();
+        let rule = self.space.rule().to_string();
+        Pattern::new(cells, width as i32, height as i32, Some(rule), vec![], 0)
+    }
+
+    /// `copy_rect`, then `clear_rect`.
+    pub fn cut_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) -> Pattern {
+        let pattern = self.copy_rect(x0, y0, x1, y1);
+        self.clear_rect(x0, y0, x1, y1);
+        pattern
     }
 
-    pub fn population(&mut self) -> usize {
-        let space_tree = self.root_id.fetch_from(&self.space);
-        space_tree.population()
+    /// Kill every cell inside the inclusive rectangle (x0, y0)-(x1, y1).
+    pub fn clear_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        self.clip_rect(x0, y0, x1, y1, false);
     }
 
-    #[wasm_bindgen]
-    pub fn from_rle(&mut self, rle_str: String) {
-        let rows = rle_str.split("\n");
-        let pattern_data_re = Regex::new(r"^[#|x|x=]").unwrap();
-        let header_pat = Regex::new(r"x\s*=\s*[0-9]+|y\s*=\s*[0-9]+|rule\s*=.+/").unwrap();
-    
-        let mut data_lines = vec![];
-        let mut header_lines = vec![];
-    
-        for row in rows {
-            if header_pat.is_match(row) {
-                header_lines.push(row);
+    /// Kill every cell outside the inclusive rectangle (x0, y0)-(x1, y1).
+    pub fn clear_outside(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        self.clip_rect(x0, y0, x1, y1, true);
+    }
+
+    fn clip_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, keep_inside: bool) {
+        let area = cell_rect(x0, y0, x1, y1);
+        self.record(|uni| {
+            let rect = uni.relative(&area);
+            uni.root_id = uni.space.clip(uni.root_id, rect, keep_inside);
+            uni.contract_root();
+            uni.maybe_collect();
+        });
+    }
+
+    /// Set every cell inside the inclusive rectangle (x0, y0)-(x1, y1) to `state`.
+    pub fn fill_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, state: usize) -> Result<(), String> {
+        if state >= rule::MAX_STATES {
+            return Err(format!("cell state {} is out of range", state));
+        }
+        let area = cell_rect(x0, y0, x1, y1);
+        self.record(|uni| {
+            let rect = uni.relative(&area);
+            uni.root_id = uni.space.clip(uni.root_id, rect, false);
+            if state == 0 {
+                uni.contract_root();
+                uni.maybe_collect();
+            } else {
+                // Sized to the rectangle rather than the root, so a far-flung root can't overflow
+                // the filled tree's population; its interior still shares one node per level.
+                let (width, height) = (area.width(), area.height());
+                let filled = uni.space.filled_tree(level_for(width.max(height)), state);
+                let piece = uni.space.clip(filled, (0, 0, width - 1, height - 1), true);
+                uni.graft(piece, area.min_x, area.min_y, &area, gol::SetOp::Union);
             }
-            if !pattern_data_re.is_match(row.trim()) {
-                data_lines.push(row);
+        });
+        Ok(())
+    }
+
+    /// Replace the inclusive rectangle (x0, y0)-(x1, y1) with a random soup in which each cell
+    /// is alive with probability `density`. The same seed always gives the same soup.
+    pub fn random_fill(
+        &mut self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+        density: f64,
+        seed: u64,
+    ) -> Result<(), String> {
+        self.random_fill_symmetric(x0, y0, x1, y1, density, seed, Symmetry::C1)
+    }
+
+    /// `random_fill` with the soup made symmetric about the rectangle's centre.
+    #[allow(clippy::too_many_arguments)]
+    pub fn random_fill_symmetric(
+        &mut self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+        density: f64,
+        seed: u64,
+        symmetry: Symmetry,
+    ) -> Result<(), String> {
+        let area = cell_rect(x0, y0, x1, y1);
+        let (width, height) = (area.width(), area.height());
+        let cells = soup::generate(width, height, density, seed, symmetry)?
+            .into_iter()
+            .map(|(x, y)| (x as i32, y as i32, 1))
+            .collect();
+        let pattern = Pattern::new(cells, width as i32, height as i32, None, vec![], 0);
+        self.paste_at(&pattern, area.min_x, area.min_y, PasteMode::Copy);
+        Ok(())
+    }
+
+    /// Combine `piece`, with its lower corner placed at (x, y), into the root using `op`.
+    /// `frame` must hold every non-dead cell of the piece.
+    fn graft(&mut self, piece: gol::ID, x: i64, y: i64, frame: &BoundingBox, op: gol::SetOp) {
+        self.expand_to(frame);
+        let placed = self.space.window(
+            piece,
+            self.root_x - x,
+            self.root_y - y,
+            self.space.level(self.root_id),
+        );
+        self.root_id = self.space.combine(self.root_id, placed, op);
+        self.contract_root();
+        self.maybe_collect();
+    }
+
+    /// `area` relative to the root's lower corner, as an inclusive `(x0, y0, x1, y1)`.
+    fn relative(&self, area: &BoundingBox) -> (i64, i64, i64, i64) {
+        (
+            area.min_x - self.root_x,
+            area.min_y - self.root_y,
+            area.max_x - self.root_x,
+            area.max_y - self.root_y,
+        )
+    }
+
+    /// Add every live cell of `other`, at the same absolute coordinates.
+    pub fn union_with(&mut self, other: &Universe) {
+        self.record(|uni| uni.combine(other, gol::SetOp::Union));
+    }
+
+    /// Keep only the cells that are also live in `other`.
+    pub fn intersect_with(&mut self, other: &Universe) {
+        self.record(|uni| uni.combine(other, gol::SetOp::Intersect));
+    }
+
+    /// Kill every cell that is live in `other`, e.g. to cut known debris out of a pattern.
+    pub fn subtract(&mut self, other: &Universe) {
+        self.record(|uni| uni.combine(other, gol::SetOp::Subtract));
+    }
+
+    /// Keep the cells live in exactly one of the two universes, e.g. to compare two runs.
+    pub fn xor_with(&mut self, other: &Universe) {
+        self.record(|uni| uni.combine(other, gol::SetOp::Xor));
+    }
+
+    /// Bring `other`'s tree into this universe's space, line it up with the root and combine.
+    fn combine(&mut self, other: &Universe, op: gol::SetOp) {
+        let bbox = match other.bounding_box() {
+            Some(bbox) => bbox,
+            None => {
+                // Only an intersection with nothing changes anything.
+                if op == gol::SetOp::Intersect {
+                    self.root_id = self.space.empty_tree(MIN_ROOT_LEVEL);
+                }
+                return;
             }
+        };
+        let imported = self.space.import(&other.space, other.root_id);
+        self.graft(imported, other.root_x, other.root_y, &bbox, op);
+    }
+
+    /// Place `pattern` with its top-left corner at (x, y) in one undoable step.
+    ///
+    /// The pattern is built into a tree of its own and merged with the universe node by node,
+    /// rather than cell by cell. Its rule and comments are ignored.
+    pub fn paste(&mut self, pattern: &Pattern, x: f64, y: f64, mode: PasteMode) {
+        self.paste_at(pattern, cell_coord(x), cell_coord(y), mode);
+    }
+
+    fn paste_at(&mut self, pattern: &Pattern, x: i64, y: i64, mode: PasteMode) {
+        let cells = pattern.cells();
+        let width = cells.iter().map(|c| c.0 + 1).fold(pattern.width(), i32::max) as i64;
+        let height = cells.iter().map(|c| c.1 + 1).fold(pattern.height(), i32::max) as i64;
+        if width <= 0 || height <= 0 {
+            return;
         }
-    
-        let header_data_pat = Regex::new(r"x\s*=\s*[0-9]+|y\s*=\s*[0-9]+|rule\s*=.+").unwrap();
-        let header_data: Vec<&str> = header_data_pat.find_iter(header_lines[0]).map(|m| m.as_str()).collect();
-        
-        // pattern width & height:
-        let width = header_data[0].split('=').last().unwrap().trim().parse::<i32>().unwrap();
-        let height = header_data[1].split('=').last().unwrap().trim().parse::<i32>().unwrap();
-    
-        
-        let pattern_data = data_lines.join("").to_string();
-        let token_pat = Regex::new(r"[0-9]+|o|b|!|\$").unwrap();
-        let tokens: Vec<&str> = token_pat.find_iter(&pattern_data).map(|m| m.as_str()).filter(|m| !m.is_empty()).collect();
-        
-        let digit_pat = Regex::new(r"[0-9]+").unwrap();
-        let mut curr_num = 1;
-        let mut coords: Vec<(i32, i32)> = vec![];
-        let mut x = 0;
-        let mut y = 0;
-        
-        for token in tokens {
-            if digit_pat.is_match(token) {
-                curr_num = token.parse::<i32>().unwrap();
-            } else if token == "o" {
-                for i in x..(x + curr_num) {
-                    println!("C: i:{:?} x:{:?} curr_num: {:?}", i, x, curr_num);
-                    coords.push((i, y));
-                    self.set(i, y);
-                }
-                println!("---------");
-                x += curr_num;
-                curr_num = 1
-            } else if token == "b" {
-                x += curr_num;
-                curr_num = 1;
-            } else if token == "$" {
-                for _ in 0..(curr_num - 1) {
-                    y += 1;
-                }
-                x = 0;
-                y += 1;
-                println!("Y: {:?}", y);
-                curr_num = 1;
+        let frame = BoundingBox::from_corners(x, y, x + width - 1, y + height - 1);
+        self.record(|uni| {
+            let level = level_for(width.max(height));
+            let mut piece = uni.space.empty_tree(level);
+            for &(cx, cy, state) in cells {
+                piece = uni.space.set_tree_pos(piece, cx as u64, cy as u64, state);
             }
+            let op = match mode {
+                PasteMode::Or => gol::SetOp::Union,
+                PasteMode::Xor => gol::SetOp::Xor,
+                PasteMode::Erase => gol::SetOp::Subtract,
+                PasteMode::Copy => {
+                    let area = uni.relative(&frame);
+                    uni.root_id = uni.space.clip(uni.root_id, area, false);
+                    gol::SetOp::Union
+                }
+            };
+            uni.graft(piece, x, y, &frame, op);
+        });
+    }
+
+    /// `paste` for RLE text, subject to the limits set with `set_rle_limits`.
+    pub fn paste_rle(&mut self, text: &str, x: f64, y: f64, mode: PasteMode) -> Result<(), RleError> {
+        let pattern = Pattern::parse_rle(text, &self.rle_limits)?;
+        self.paste(&pattern, x, y, mode);
+        Ok(())
+    }
+
+    /// `paste` for a plaintext `.cells` pattern.
+    pub fn paste_cells(&mut self, text: &str, x: f64, y: f64, mode: PasteMode) -> Result<(), String> {
+        let pattern = Pattern::from_cells(text)?;
+        self.paste(&pattern, x, y, mode);
+        Ok(())
+    }
+
+    /// Expand the root until it covers `area`.
+    fn expand_to(&mut self, area: &BoundingBox) {
+        while !self.contains(area.min_x, area.min_y) || !self.contains(area.max_x, area.max_y) {
+            self.expand_root();
+        }
+    }
+
+    /// Width and height of the root square.
+    fn root_dim(&self) -> i64 {
+        1i64 << self.space.level(self.root_id)
+    }
+
+    fn contains(&self, x: i64, y: i64) -> bool {
+        let root_dim = self.root_dim();
+        x >= self.root_x && y >= self.root_y && x < self.root_x + root_dim && y < self.root_y + root_dim
+    }
+
+    /// Replace the root with its smallest subtree that still holds every live cell.
+    fn contract_root(&mut self) {
+        let (root_id, x, y) = self.space.contract(self.root_id, MIN_ROOT_LEVEL);
+        self.root_id = root_id;
+        self.root_x += x as i64;
+        self.root_y += y as i64;
+    }
+
+    /// Double the root around its centre.
+    fn expand_root(&mut self) {
+        let root_dim = self.root_dim();
+        self.root_id = self.space.expand_tree(self.root_id);
+        self.root_x -= root_dim / 2;
+        self.root_y -= root_dim / 2;
+    }
+
+    /// State of the cell at absolute coordinates (x, y); anything outside the root is dead.
+    pub fn get(&self, x: f64, y: f64) -> usize {
+        let (x, y) = (cell_coord(x), cell_coord(y));
+        if !self.contains(x, y) {
+            return 0;
         }
+        self.space.get_tree_cell(
+            self.root_id,
+            (x - self.root_x) as u64,
+            (y - self.root_y) as u64,
+        )
+    }
 
+    /// Batched `get`: takes flattened `[x0, y0, x1, y1, ...]` and returns one state per pair.
+    pub fn get_many(&self, coords: &[f64]) -> Array {
+        self.get_cells(coords)
+            .into_iter()
+            .map(|state| JsValue::from(state as u32))
+            .collect()
     }
 
-    pub fn set(&mut self, x: i32, y: i32) {
-        let mut root_dim = 2i32.pow(self.root_id.fetch_from(&self.space).level() as u32);
-        while x < self.rootX
-            || y < self.rootY
-            || x >= self.rootX + root_dim
-            || y >= self.rootY + root_dim
-        {
-            self.root_id = self.space.expand_tree(self.root_id);
-            self.rootX -= root_dim / 2;
-            self.rootY -= root_dim / 2;
-            root_dim = 2i32.pow(self.root_id.fetch_from(&self.space).level() as u32);
+    fn set_cell(&mut self, x: i64, y: i64, state: usize) {
+        // Everything outside the root is already dead, so there is nothing to clear.
+        if state == 0 && !self.contains(x, y) {
+            return;
+        }
+        while !self.contains(x, y) {
+            self.expand_root();
         }
-        let adjusted_x = x - self.rootX;
-        let adjusted_y = y - self.rootY;
+        let adjusted_x = x - self.root_x;
+        let adjusted_y = y - self.root_y;
 
         // Set cell relative to top left corner of space
         self.root_id = self
             .space
-            .set_tree_pos(self.root_id, adjusted_x as usize, adjusted_y as usize, 1);
+            .set_tree_pos(self.root_id, adjusted_x as u64, adjusted_y as u64, state);
+        // Only clearing a cell can leave the pattern small enough for a smaller root.
+        if state == 0 {
+            self.contract_root();
+        }
+        self.maybe_collect();
     }
 
 
-    pub fn coords(&mut self, min_x: i32, min_y: i32, max_x: i32, max_y: i32) -> Array {
+    /// Live cells inside the given bounds, flattened as `[x, y, state, x, y, state, ...]`.
+    pub fn coords(&mut self, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Array {
         let coords = self.space.get_coords(
             self.root_id,
-            self.rootX,
-            self.rootY,
-            min_x,
-            min_y,
-            max_x,
-            max_y,
+            self.root_x,
+            self.root_y,
+            cell_coord(min_x),
+            cell_coord(min_y),
+            cell_coord(max_x),
+            cell_coord(max_y),
         );
-        let mut results: Vec<i32> = vec![];
-        for (cx, cy) in coords {
-            results.push(cx);
-            results.push(cy);
+        let mut results: Vec<f64> = vec![];
+        for (cx, cy, state) in coords {
+            results.push(cx as f64);
+            results.push(cy as f64);
+            results.push(state as f64);
         }
         results.into_iter().map(JsValue::from).collect()
     }
 
-    pub fn coords_level(&mut self, min_x: i32, min_y: i32, max_x: i32, max_y: i32, level: usize) -> Array {
+    pub fn coords_level(&mut self, min_x: f64, min_y: f64, max_x: f64, max_y: f64, level: usize) -> Array {
         let coords = self.space.get_coords_level(
             self.root_id,
-            self.rootX,
-            self.rootY,
-            min_x,
-            min_y,
-            max_x,
-            max_y,
+            self.root_x,
+            self.root_y,
+            cell_coord(min_x),
+            cell_coord(min_y),
+            cell_coord(max_x),
+            cell_coord(max_y),
             level
         );
-        let mut results: Vec<i32> = vec![];
+        let mut results: Vec<f64> = vec![];
         for (cx, cy, p) in coords {
-            results.push(cx);
-            results.push(cy);
-            results.push(p as i32);
+            results.push(cx as f64);
+            results.push(cy as f64);
+            results.push(p as f64);
         }
         results.into_iter().map(JsValue::from).collect()
     }
 
 
-    pub fn center(&mut self, n: usize) {
-        let mut tid = self.root_id;
+    /// Split the pattern into separate objects and identify each, running every object for up
+    /// to `max_period` generations. The universe itself is not advanced.
+    pub fn census(&self, max_period: u32) -> Census {
+        census::take(&self.live_cells(), self.space.rule(), max_period)
+    }
+
+    /// Matches of `search` flattened as `[x, y, orientation, ...]`, ordered top to bottom.
+    pub fn find_pattern(&self, search: &Search) -> Array {
+        self.find_matches(search)
+            .into_iter()
+            .flat_map(|m| [m.x as f64, m.y as f64, m.orientation as f64])
+            .map(JsValue::from)
+            .collect()
+    }
+
+    /// Extent of the live cells, or `None` if the universe is empty.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        let (min_x, min_y, max_x, max_y) = self.space.bounding_box(self.root_id)?;
+        Some(BoundingBox {
+            min_x: self.root_x + min_x as i64,
+            min_y: self.root_y + min_y as i64,
+            max_x: self.root_x + max_x as i64,
+            max_y: self.root_y + max_y as i64,
+        })
+    }
+
+    /// A view framing the whole pattern on a screen of the given size, or `None` if the
+    /// universe is empty or the screen has no area.
+    pub fn fit_view(&self, screen_width: f64, screen_height: f64) -> Option<View> {
+        if !(screen_width > 0.0 && screen_height > 0.0) {
+            return None;
+        }
+        let bbox = self.bounding_box()?;
+        Some(view::fit(&bbox, screen_width / screen_height))
+    }
+
+    /// Expand the root until every live cell lies in its central quarter, the region from
+    /// which a step of up to a quarter of the root's width cannot escape.
+    pub fn center(&mut self, _n: usize) {
         loop {
-            let tree = tid.fetch_node(&self.space);
-
-            let (nw_pop, ne_pop, sw_pop, se_pop) = (
-                tree.north_west.fetch_from(&self.space).population(),
-                tree.north_east.fetch_from(&self.space).population(),
-                tree.south_west.fetch_from(&self.space).population(),
-                tree.south_east.fetch_from(&self.space).population(),
-            );
-
-            let (nw_inner_pop, ne_inner_pop, sw_inner_pop, se_inner_pop) = (
-                tree.north_west
-                    .fetch_node(&self.space)
-                    .south_east
-                    .fetch_node(&self.space)
-                    .south_east
-                    .fetch_from(&self.space)
-                    .population(),
-                tree.north_east
-                    .fetch_node(&self.space)
-                    .south_west
-                    .fetch_node(&self.space)
-                    .south_west
-                    .fetch_from(&self.space)
-                    .population(),
-                tree.south_west
-                    .fetch_node(&self.space)
-                    .north_east
-                    .fetch_node(&self.space)
-                    .north_east
-                    .fetch_from(&self.space)
-                    .population(),
-                tree.south_east
-                    .fetch_node(&self.space)
-                    .north_west
-                    .fetch_node(&self.space)
-                    .north_west
-                    .fetch_from(&self.space)
-                    .population(),
-            );
-
-            if tid.fetch_from(&self.space).level() >= 3
-                && nw_pop == nw_inner_pop
-                && ne_pop == ne_inner_pop
-                && sw_pop == sw_inner_pop
-                && se_pop == se_inner_pop
-            {
+            let tree = self.root_id.fetch_node(&self.space);
+            let sp = &self.space;
+            let inner = |child: gol::ID, pick: fn(&gol::Node) -> gol::ID| {
+                let grandchild = pick(child.fetch_node(sp));
+                sp.population(pick(grandchild.fetch_node(sp)))
+            };
+            let centered = sp.population(tree.north_west) == inner(tree.north_west, |n| n.south_east)
+                && sp.population(tree.north_east) == inner(tree.north_east, |n| n.south_west)
+                && sp.population(tree.south_west) == inner(tree.south_west, |n| n.north_east)
+                && sp.population(tree.south_east) == inner(tree.south_east, |n| n.north_west);
+            if centered {
                 break;
             }
+            self.expand_root();
+        }
+    }
 
-            let root_dim = 2i32.pow(tid.fetch_from(&self.space).level() as u32);
-            tid = self.space.expand_tree(tid);
-            self.rootX -= root_dim / 2;
-            self.rootY -= root_dim / 2;
+    /// Advance exactly `2^j` generations; `j` must be below 64.
+    pub fn advance_pow2(&mut self, j: u32) -> Result<(), String> {
+        if j >= 64 {
+            return Err(format!("cannot advance 2^{} generations", j));
         }
-        self.root_id = tid;
+        self.advance_by(1 << j);
+        Ok(())
     }
 
-    pub fn advance(&mut self, steps: usize) {
+    /// Advance exactly `n` generations, one power-of-two step per set bit of `n`.
+    ///
+    /// Coordinates are 64-bit, so live cells have to stay within about 2^62 cells of the origin.
+    pub fn advance_by(&mut self, n: u64) {
+        self.record(|uni| {
+            for bit in 0..64 {
+                if n & (1 << bit) == 0 {
+                    continue;
+                }
+                if bit <= MAX_STEP_POW {
+                    uni.step(bit);
+                } else {
+                    for _ in 0..1u64 << (bit - MAX_STEP_POW) {
+                        uni.step(MAX_STEP_POW);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Advance `2^j` generations, expanding the root first so nothing can leave it.
+    fn step(&mut self, j: usize) {
+        self.step_uncollected(j);
+        self.maybe_collect();
+    }
+
+    /// `step` without the garbage collection, so IDs held outside the universe stay valid.
+    fn step_uncollected(&mut self, j: usize) {
+        self.generation += 1 << j;
+        if self.space.population(self.root_id) == 0 {
+            return;
+        }
         self.center(0);
-        let mut tid = self.root_id;
-        if tid.fetch_from(&self.space).population() == 0usize {
+        while self.space.level(self.root_id) < j + 3 {
+            self.expand_root();
+        }
+        // The result is the root's centred half, a quarter of the old width in from each edge.
+        let margin = self.root_dim() / 4;
+        self.root_id = self.space.evolve_tree(self.root_id, j);
+        self.root_x += margin;
+        self.root_y += margin;
+        self.contract_root();
+    }
+
+    /// The live cells as a tree whose lower corner is their bounding box's lower corner, so
+    /// that two translated copies of a pattern get the same ID. Also returns that corner.
+    fn normalised(&mut self) -> Option<(gol::ID, i64, i64)> {
+        let bbox = self.bounding_box()?;
+        let size = bbox.width().max(bbox.height()) as u64;
+        let level = size.next_power_of_two().trailing_zeros() as usize;
+        let id = self.space.window(
+            self.root_id,
+            bbox.min_x - self.root_x,
+            bbox.min_y - self.root_y,
+            level,
+        );
+        Some((id, bbox.min_x, bbox.min_y))
+    }
+
+    /// Run the pattern for up to `max_period` generations, looking for a state that repeats an
+    /// earlier one, either in place or translated. The universe is left exactly as it was.
+    ///
+    /// States are compared by the ID of their translation-normalised tree, so spotting a
+    /// displaced copy costs no more than spotting an unmoved one.
+    pub fn detect_period(&mut self, max_period: u32) -> Periodicity {
+        let saved = self.current();
+        // No collection runs until the end, so the IDs recorded here stay valid throughout.
+        let mut seen = HashMap::new();
+        let mut result = Periodicity::not_periodic();
+        for gen in 0..=max_period {
+            if gen > 0 {
+                self.step_uncollected(0);
+            }
+            match self.normalised() {
+                None => {
+                    result = Periodicity::empty(gen);
+                    break;
+                }
+                Some((id, x, y)) => {
+                    if let Some(&(start, x0, y0)) = seen.get(&id) {
+                        result = Periodicity::periodic(start, gen - start, x - x0, y - y0);
+                        break;
+                    }
+                    seen.insert(id, (gen, x, y));
+                }
+            }
+        }
+        self.restore(saved);
+        self.maybe_collect();
+        result
+    }
+
+    fn current(&self) -> Snapshot {
+        Snapshot {
+            root_id: self.root_id,
+            root_x: self.root_x,
+            root_y: self.root_y,
+            generation: self.generation,
+        }
+    }
+
+    fn restore(&mut self, snap: Snapshot) {
+        self.root_id = snap.root_id;
+        self.root_x = snap.root_x;
+        self.root_y = snap.root_y;
+        self.generation = snap.generation;
+    }
+
+    /// Run `edit` as one undoable change.
+    ///
+    /// The prior state goes on the undo stack before `edit` runs, so a collection triggered by
+    /// the edit keeps its nodes alive; it is dropped again if the edit changed nothing.
+    fn record(&mut self, edit: impl FnOnce(&mut Universe)) {
+        self.undo_stack.push_back(self.current());
+        edit(self);
+        if self.undo_stack.back() == Some(&self.current()) {
+            self.undo_stack.pop_back();
             return;
         }
-        // let mut bits = vec![];
-        let mut steps_count = steps;
-        // while steps_count > 0 {
-            // bits.push(steps_count & 1);
-            // steps_count = steps_count >> 1;
-            // let root_dim = 2i32.pow(tid.fetch_from(&self.space).level() as u32);
-            // tid = self.space.expand_tree(tid);
-            // self.rootX -= root_dim / 2;
-            // self.rootY -= root_dim / 2;
-        // }
-        // self.center(0);
-
-        // let mut k = 0;
-        // for bit in bits.iter().rev() {
-        //     let j = bits.len() - k - 1;
-        //     if *bit != 0 {
-        //         let rd = 2i32.pow(tid.fetch_from(&self.space).level() as u32 - 1);
-        //         // self.center(0);
-        //         // tid = self.space.expand_tree(tid);
-        //         // self.rootX -= rd/2;
-        //         // self.rootY -= rd/2;
-        //         tid = self.space.evolve_tree(tid, steps);
-        //         self.rootX += rd / 2;
-        //         self.rootY += rd / 2;
-        //     }
-        // }
-        let rd = 2i32.pow(tid.fetch_from(&self.space).level() as u32 - 1);
-        tid = self.space.evolve_tree(tid, steps);
-        self.rootX += rd / 2;
-        self.rootY += rd / 2;
-        self.root_id = tid
+        self.redo_stack.clear();
+        while self.undo_stack.len() > self.undo_depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// Make the current state the one `reset_to_start` returns to, forgetting all history.
+    fn mark_start(&mut self) {
+        self.start = self.current();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Revert the last edit or advance; returns false when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop_back() {
+            Some(snap) => {
+                self.redo_stack.push(self.current());
+                self.restore(snap);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-apply the last undone change; returns false when there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(snap) => {
+                self.undo_stack.push_back(self.current());
+                self.restore(snap);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Go back to the state right after the last load. This is itself undoable.
+    pub fn reset_to_start(&mut self) {
+        let start = self.start;
+        self.record(|uni| uni.restore(start));
+    }
+
+    /// Limit how many changes `undo` can revert, discarding the oldest beyond `depth`.
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_depth = depth;
+        while self.undo_stack.len() > depth {
+            self.undo_stack.pop_front();
+        }
     }
+
+    /// Pin the current state so that it survives garbage collection; returns its index.
+    pub fn snapshot(&mut self) -> usize {
+        self.snapshots.push(self.current());
+        self.snapshots.len() - 1
+    }
+
+    /// Return to a state previously pinned with `snapshot`; returns false, leaving the universe
+    /// as it is, when `index` does not name a pinned snapshot.
+    pub fn restore_snapshot(&mut self, index: usize) -> bool {
+        match self.snapshots.get(index) {
+            Some(&snap) => {
+                self.restore(snap);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop all pinned snapshots, allowing their nodes to be collected.
+    pub fn clear_snapshots(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Set how many interned nodes may accumulate before a collection runs automatically.
+    pub fn set_gc_threshold(&mut self, threshold: usize) {
+        self.space.set_gc_threshold(threshold);
+    }
+
+    /// Number of nodes currently held by the underlying `Space`.
+    pub fn node_count(&self) -> usize {
+        self.space.len()
+    }
+
+    /// Free every node not reachable from the current root, a pinned snapshot or the undo
+    /// history.
+    pub fn collect_garbage(&mut self) {
+        let mut roots = vec![self.root_id];
+        roots.extend(self.saved_states().map(|snap| snap.root_id));
+        self.space.collect_garbage(&mut roots);
+        self.root_id = roots[0];
+        for (snap, root) in self.saved_states().zip(&roots[1..]) {
+            snap.root_id = *root;
+        }
+    }
+
+    /// Every stored state whose root must survive garbage collection.
+    fn saved_states(&mut self) -> impl Iterator<Item = &mut Snapshot> {
+        self.snapshots
+            .iter_mut()
+            .chain(self.undo_stack.iter_mut())
+            .chain(self.redo_stack.iter_mut())
+            .chain(std::iter::once(&mut self.start))
+    }
+
+    fn maybe_collect(&mut self) {
+        if self.space.needs_gc() {
+            self.collect_garbage();
+        }
+    }
+    /// Set the evolution rule from a rulestring such as `B36/S23` or `23/36`.
+    pub fn set_rule(&mut self, rule: &str) -> Result<(), String> {
+        self.space.set_rule(rule.parse()?);
+        Ok(())
+    }
+
+    /// The current rule in canonical `B.../S...` form.
+    pub fn rule(&self) -> String {
+        self.space.rule().to_string()
+    }
+
+    /// Generations elapsed since the pattern was loaded, as a decimal string since the count
+    /// can exceed what a JS number holds exactly.
+    pub fn generation(&self) -> String {
+        self.generation.to_string()
+    }
+
     pub fn root_level(&self) -> usize {
-        self.root_id.fetch_from(&self.space).level()
+        self.space.level(self.root_id)
     }
     /// Get the minimum X coordinate contained in the Universe
-    pub fn root_x(&self) -> i32 {
-        self.rootX
+    pub fn root_x(&self) -> f64 {
+        self.root_x as f64
     }
     /// Get the minimum Y coordinate contained in the Universe
-    pub fn root_y(&self) -> i32 {
-        self.rootY
+    pub fn root_y(&self) -> f64 {
+        self.root_y as f64
     }
 }
+
+impl Universe {
+    /// The state of each (x, y) pair in flattened `coords`, as `get_many` reports to JS. A
+    /// trailing unpaired value is ignored.
+    pub fn get_cells(&self, coords: &[f64]) -> Vec<usize> {
+        coords.chunks_exact(2).map(|xy| self.get(xy[0], xy[1])).collect()
+    }
+
+    /// Number of cells in each state, as `population_by_state` reports to JS.
+    pub fn state_populations(&self) -> Vec<usize> {
+        self.space.population_by_state(self.root_id)
+    }
+
+    /// Every match of `search`, ordered top to bottom. Only the non-empty parts of the tree
+    /// around the searched area are visited.
+    pub fn find_matches(&self, search: &Search) -> Vec<SearchMatch> {
+        let region = match self.bounding_box() {
+            Some(bbox) => search.region(&bbox),
+            None => return vec![],
+        };
+        let (left, top, right, bottom) = search.margin();
+        let (x0, y0, x1, y1) = (region.0 - left, region.1 - top, region.2 + right, region.3 + bottom);
+        let cells = self
+            .space
+            .get_coords(self.root_id, self.root_x, self.root_y, x0, y0, x1, y1)
+            .into_iter()
+            .filter(|&(x, y, _)| x >= x0 && x <= x1 && y >= y0 && y <= y1)
+            .map(|(x, y, state)| ((x, y), state))
+            .collect();
+        search.run(&cells, region)
+    }
+}
+
+/// A coordinate passed in from JS as a number, rounded down to the cell containing it. JS
+/// numbers hold integers exactly up to 2^53 in magnitude.
+fn cell_coord(v: f64) -> i64 {
+    v.floor() as i64
+}
+
+/// The inclusive rectangle with opposite corners (x0, y0) and (x1, y1), in either order, as
+/// passed in from JS.
+fn cell_rect(x0: f64, y0: f64, x1: f64, y1: f64) -> BoundingBox {
+    BoundingBox::from_corners(cell_coord(x0), cell_coord(y0), cell_coord(x1), cell_coord(y1))
+}
+
+/// Smallest tree level whose square is at least `size` cells wide.
+fn level_for(size: i64) -> usize {
+    (size as u64).next_power_of_two().trailing_zeros() as usize
+}
@@ -0,0 +1,229 @@
+//! Golly-compatible Macrocell (`.mc`) reading and writing.
+//!
+//! A Macrocell file is a list of quadtree nodes, one per line, where children refer to earlier
+//! lines by their 1-based index and 0 stands for an empty subtree. Two-state files store
+//! level-3 (8x8) nodes as rows of `.`/`*` separated by `$`; multi-state files instead write
+//! level-1 nodes whose children are cell states. Lines therefore map one-to-one onto `Space`
+//! nodes in both directions.
+//!
+//! Macrocell's `nw`/`ne`/`sw`/`se` use a y-down orientation, so its north half is our south half.
+
+use crate::gol::{Space, ID};
+use crate::rule::{strip_bounds, MAX_STATES};
+use std::collections::HashMap;
+
+/// The deepest node a file may define, so that its root, centred on the origin, still has
+/// corners that fit in an `i64`.
+const MAX_LEVEL: usize = 62;
+
+pub struct Macrocell {
+    pub root: ID,
+    pub rule: Option<String>,
+    pub comments: Vec<String>,
+    /// From the `#G` line, 0 if there was none.
+    pub generation: u128,
+}
+
+/// Build the level-3 node for an 8x8 grid of states indexed `[y][x]`.
+fn build_leaf_block(space: &mut Space, grid: &[[usize; 8]; 8], x: usize, y: usize, size: usize) -> ID {
+    if size == 1 {
+        return space.new_leaf(grid[y][x]);
+    }
+    let half = size / 2;
+    let sw = build_leaf_block(space, grid, x, y, half);
+    let se = build_leaf_block(space, grid, x + half, y, half);
+    let nw = build_leaf_block(space, grid, x, y + half, half);
+    let ne = build_leaf_block(space, grid, x + half, y + half, half);
+    space.new_node(nw, ne, sw, se)
+}
+
+fn parse_leaf_block(space: &mut Space, line: &str, line_no: usize) -> Result<ID, String> {
+    let mut grid = [[0usize; 8]; 8];
+    for (y, row) in line.split('$').enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if x >= 8 || y >= 8 {
+                return Err(format!("line {}: 8x8 leaf block is too large", line_no));
+            }
+            match c {
+                '.' => {}
+                '*' => grid[y][x] = 1,
+                _ => return Err(format!("line {}: unexpected '{}' in leaf block", line_no, c)),
+            }
+        }
+    }
+    Ok(build_leaf_block(space, &grid, 0, 0, 8))
+}
+
+/// Read a Macrocell file, interning its nodes into `space`.
+pub fn read(space: &mut Space, text: &str) -> Result<Macrocell, String> {
+    let mut lines = text.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.starts_with("[M2]") => {}
+        _ => return Err("missing [M2] header line".to_string()),
+    }
+
+    let mut rule = None;
+    let mut comments = vec![];
+    let mut generation = 0;
+    // `nodes[i]` is the node defined on the i-th node line, `nodes[0]` unused.
+    let mut nodes: Vec<(ID, usize)> = vec![(space.new_leaf(0), 0)];
+    for (i, line) in lines {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(r) = line.strip_prefix("#R") {
+            rule = Some(strip_bounds(r).to_string());
+        } else if let Some(g) = line.strip_prefix("#G") {
+            generation = g
+                .trim()
+                .parse()
+                .map_err(|_| format!("line {}: invalid generation '{}'", line_no, g.trim()))?;
+        } else if line.starts_with('#') {
+            if ["#N", "#O", "#C", "#c"].iter().any(|tag| line.starts_with(tag)) {
+                comments.push(line.to_string());
+            }
+        } else if line.starts_with(['.', '*', '$']) {
+            let id = parse_leaf_block(space, line, line_no)?;
+            nodes.push((id, 3));
+        } else {
+            let fields: Vec<usize> = line
+                .split_whitespace()
+                .map(|f| f.parse::<usize>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("line {}: malformed node line '{}'", line_no, line))?;
+            let (level, children) = match fields.as_slice() {
+                [level, nw, ne, sw, se] if *level >= 1 => (*level, [*nw, *ne, *sw, *se]),
+                _ => return Err(format!("line {}: malformed node line '{}'", line_no, line)),
+            };
+            if level > MAX_LEVEL {
+                return Err(format!("line {}: level {} is above the maximum of {}", line_no, level, MAX_LEVEL));
+            }
+            let mut ids = [space.new_leaf(0); 4];
+            for (slot, &child) in ids.iter_mut().zip(children.iter()) {
+                *slot = if level == 1 {
+                    if child >= MAX_STATES {
+                        return Err(format!("line {}: cell state {} is out of range", line_no, child));
+                    }
+                    space.new_leaf(child)
+                } else if child == 0 {
+                    space.empty_tree(level - 1)
+                } else {
+                    match nodes.get(child) {
+                        Some(&(id, child_level)) if child_level == level - 1 => id,
+                        _ => {
+                            return Err(format!(
+                                "line {}: child {} is not a level {} node",
+                                line_no,
+                                child,
+                                level - 1
+                            ))
+                        }
+                    }
+                };
+            }
+            let [nw, ne, sw, se] = ids;
+            nodes.push((space.new_node(sw, se, nw, ne), level));
+        }
+    }
+
+    match nodes.last() {
+        Some(&(root, level)) if level > 0 => Ok(Macrocell {
+            root,
+            rule,
+            comments,
+            generation,
+        }),
+        _ => Err("Macrocell file contains no nodes".to_string()),
+    }
+}
+
+fn leaf_block_line(space: &Space, id: ID) -> String {
+    let mut rows: Vec<String> = (0..8)
+        .map(|y| {
+            let row: String = (0..8)
+                .map(|x| if space.get_tree_cell(id, x, y) != 0 { '*' } else { '.' })
+                .collect();
+            row.trim_end_matches('.').to_string()
+        })
+        .collect();
+    while rows.last().is_some_and(|r| r.is_empty()) {
+        rows.pop();
+    }
+    rows.iter().map(|r| format!("{}$", r)).collect()
+}
+
+/// Write the tree rooted at `root` as Macrocell, one line per distinct non-empty node.
+pub fn write(
+    space: &Space,
+    root: ID,
+    rule: &str,
+    multi_state: bool,
+    comments: &[String],
+    generation: u128,
+) -> String {
+    fn visit(
+        space: &Space,
+        id: ID,
+        multi_state: bool,
+        lines: &mut Vec<String>,
+        seen: &mut HashMap<ID, usize>,
+    ) -> usize {
+        if space.population(id) == 0 {
+            return 0;
+        }
+        if let Some(&index) = seen.get(&id) {
+            return index;
+        }
+        let level = space.level(id);
+        let line = if level == 3 && !multi_state {
+            leaf_block_line(space, id)
+        } else {
+            let node = id.fetch_node(space);
+            // Macrocell order is y-down: nw, ne, sw, se = our sw, se, nw, ne.
+            let children = [node.south_west, node.south_east, node.north_west, node.north_east];
+            let indices: Vec<String> = children
+                .iter()
+                .map(|&c| match c.leaf_state() {
+                    Some(state) => state.to_string(),
+                    None => visit(space, c, multi_state, lines, seen).to_string(),
+                })
+                .collect();
+            format!("{} {}", level, indices.join(" "))
+        };
+        lines.push(line);
+        seen.insert(id, lines.len());
+        lines.len()
+    }
+
+    let mut lines = vec![];
+    if space.population(root) == 0 {
+        // The root line is mandatory even when there is nothing else to write.
+        let level = space.level(root);
+        lines.push(if level == 3 && !multi_state {
+            "$".to_string()
+        } else {
+            format!("{} 0 0 0 0", level)
+        });
+    } else {
+        visit(space, root, multi_state, &mut lines, &mut HashMap::new());
+    }
+
+    let mut out = String::from("[M2] (wasm-game-of-life)\n");
+    out.push_str(&format!("#R {}\n", rule));
+    if generation > 0 {
+        out.push_str(&format!("#G {}\n", generation));
+    }
+    for comment in comments {
+        match comment.strip_prefix('!') {
+            Some(text) => out.push_str(&format!("#C {}\n", text)),
+            None => out.push_str(&format!("{}\n", comment)),
+        }
+    }
+    for line in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
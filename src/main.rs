@@ -1,5 +1,45 @@
-// This is the program executed by `cargo run`
+// This is the program executed by `cargo run`: it prints an object census of a pattern file.
+
+use std::{env, fs, process};
+use wasm_game_of_life::Universe;
+
+const DEFAULT_MAX_PERIOD: u32 = 64;
+
+fn load(path: &str) -> Result<Universe, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut uni = Universe::new(0.0, 0.0);
+    if path.ends_with(".mc") {
+        uni.from_macrocell(&text)?;
+    } else if path.ends_with(".cells") {
+        uni.from_cells(&text, 0.0, 0.0)?;
+    } else {
+        uni.from_rle(text).map_err(|e| e.to_string())?;
+    }
+    Ok(uni)
+}
 
 pub fn main() {
-    println!("<this is placeholder text>");
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: {} <pattern.rle|.cells|.mc> [max period]", args[0]);
+        process::exit(2);
+    }
+    let max_period = match args.get(2).map(|p| p.parse()) {
+        None => DEFAULT_MAX_PERIOD,
+        Some(Ok(p)) => p,
+        Some(Err(_)) => {
+            eprintln!("max period must be a non-negative integer");
+            process::exit(2);
+        }
+    };
+    let uni = match load(&args[1]) {
+        Ok(uni) => uni,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let census = uni.census(max_period);
+    println!("{} objects, population {}", census.len(), uni.population());
+    print!("{}", census.summary());
+}
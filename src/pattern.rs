@@ -0,0 +1,157 @@
+use crate::cells;
+use crate::rle::{self, RleError, RleLimits};
+use crate::rule::Rule;
+use crate::transform::Transform;
+use wasm_bindgen::prelude::*;
+
+/// How `Universe::paste` merges a pattern with the cells already there.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteMode {
+    /// Add the pattern's cells, keeping everything else.
+    Or = 0,
+    /// Flip the cells under the pattern's live cells.
+    Xor = 1,
+    /// Replace everything inside the pattern's rectangle with the pattern.
+    Copy = 2,
+    /// Kill the cells under the pattern's live cells.
+    Erase = 3,
+}
+
+/// A pattern detached from any universe: its cells relative to the top-left corner, with `y`
+/// increasing downwards, plus the metadata read alongside them.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    cells: Vec<(i32, i32, usize)>,
+    width: i32,
+    height: i32,
+    rule: Option<String>,
+    comments: Vec<String>,
+    generation: u128,
+}
+
+impl Pattern {
+    pub fn new(
+        cells: Vec<(i32, i32, usize)>,
+        width: i32,
+        height: i32,
+        rule: Option<String>,
+        comments: Vec<String>,
+        generation: u128,
+    ) -> Pattern {
+        Pattern {
+            cells,
+            width,
+            height,
+            rule,
+            comments,
+            generation,
+        }
+    }
+
+    /// Parse RLE, rejecting input that exceeds `limits`.
+    pub fn parse_rle(text: &str, limits: &RleLimits) -> Result<Pattern, RleError> {
+        rle::parse(text, limits)
+    }
+
+    /// Non-dead cells as `(x, y, state)`.
+    pub fn cells(&self) -> &[(i32, i32, usize)] {
+        &self.cells
+    }
+
+    /// `#N`, `#O` and `#C` comment lines, verbatim.
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// The generation recorded in a `#CXRLE` line, 0 if there was none.
+    pub fn generation_count(&self) -> u128 {
+        self.generation
+    }
+}
+
+#[wasm_bindgen]
+impl Pattern {
+    /// Parse RLE with the default size limits.
+    pub fn from_rle(text: &str) -> Result<Pattern, RleError> {
+        rle::parse(text, &RleLimits::default())
+    }
+
+    /// Parse a plaintext `.cells` pattern.
+    pub fn from_cells(text: &str) -> Result<Pattern, String> {
+        let plaintext = cells::parse(text)?;
+        let cells = plaintext.cells.into_iter().map(|(x, y)| (x, y, 1)).collect();
+        Ok(Pattern::new(
+            cells,
+            plaintext.width,
+            plaintext.height,
+            None,
+            plaintext.comments,
+            0,
+        ))
+    }
+
+    /// A copy rotated or reflected within its rectangle, which keeps its top-left corner.
+    pub fn transformed(&self, transform: Transform) -> Pattern {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let cells = self
+            .cells
+            .iter()
+            .map(|&(x, y, state)| {
+                let (x, y) = transform.apply(x as i64, y as i64, width, height);
+                (x as i32, y as i32, state)
+            })
+            .collect();
+        let (width, height) = transform.size(width, height);
+        Pattern {
+            cells,
+            width: width as i32,
+            height: height as i32,
+            rule: self.rule.clone(),
+            comments: self.comments.clone(),
+            generation: self.generation,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The rulestring from the pattern's header, if it had one.
+    pub fn rule(&self) -> Option<String> {
+        self.rule.clone()
+    }
+
+    /// The recorded generation as a decimal string, since it may not fit in a JS number.
+    pub fn generation(&self) -> String {
+        self.generation.to_string()
+    }
+
+    pub fn to_rle(&self) -> String {
+        let rule: Rule = self
+            .rule
+            .as_deref()
+            .and_then(|r| r.parse().ok())
+            .unwrap_or_default();
+        let multi_state = rule.states() > 2 || self.cells.iter().any(|c| c.2 > 1);
+        let cells: Vec<_> = self.cells.iter().map(|&(x, y, state)| (x as i64, y as i64, state)).collect();
+        rle::write(
+            &cells,
+            self.width as i64,
+            self.height as i64,
+            &rule.to_string(),
+            multi_state,
+            &self.comments,
+            self.generation,
+        )
+    }
+}
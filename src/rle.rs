@@ -0,0 +1,348 @@
+//! Reading and writing the RLE pattern format.
+
+use crate::pattern::Pattern;
+use crate::rule::{strip_bounds, Rule};
+use std::fmt;
+use wasm_bindgen::JsValue;
+
+/// A parse failure, positioned at a 1-based line and column of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RleError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl RleError {
+    fn new(line: usize, column: usize, message: impl Into<String>) -> RleError {
+        RleError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for RleError {}
+
+impl From<RleError> for JsValue {
+    fn from(err: RleError) -> JsValue {
+        js_sys::Error::new(&format!("RLE {}", err)).into()
+    }
+}
+
+/// Bounds applied while parsing, so untrusted input can't request unbounded work or memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RleLimits {
+    /// Largest width or height, whether declared in the header or reached by the data.
+    pub max_dimension: u32,
+    /// Largest number of non-dead cells.
+    pub max_cells: usize,
+}
+
+impl Default for RleLimits {
+    fn default() -> RleLimits {
+        RleLimits {
+            max_dimension: 1 << 25,
+            max_cells: 1 << 24,
+        }
+    }
+}
+
+/// Validate a rulestring, dropping any bounded-grid suffix such as `:T100,100`.
+fn parse_rule(value: &str, line_no: usize, column: usize) -> Result<String, RleError> {
+    let rule = strip_bounds(value);
+    match rule.parse::<Rule>() {
+        Ok(_) => Ok(rule.to_string()),
+        Err(e) => Err(RleError::new(line_no, column, format!("unsupported rule: {}", e))),
+    }
+}
+
+/// Read the generation from the fields of a Golly `#CXRLE` line such as `Pos=0,0 Gen=120`.
+///
+/// `Pos` is not used: patterns are always placed where the caller asks.
+fn parse_extended(fields: &str, line_no: usize) -> Result<Option<u128>, RleError> {
+    let mut generation = None;
+    let mut column = "#CXRLE".len() + 1;
+    for field in fields.split(' ') {
+        if let Some(value) = field.strip_prefix("Gen=") {
+            let n = value
+                .parse::<u128>()
+                .map_err(|_| RleError::new(line_no, column, format!("invalid generation '{}'", value)))?;
+            generation = Some(n);
+        }
+        column += field.len() + 1;
+    }
+    Ok(generation)
+}
+
+fn parse_header(line: &str, line_no: usize, limits: &RleLimits) -> Result<(i32, i32, Option<String>), RleError> {
+    let (mut width, mut height, mut rule) = (None, None, None);
+    let mut column = 1;
+    for field in line.split(',') {
+        let start = column - 1;
+        let at = column + field.len() - field.trim_start().len();
+        column += field.len() + 1;
+        let (key, value) = match field.split_once('=') {
+            Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+            None => return Err(RleError::new(line_no, at, format!("expected 'key = value', found '{}'", field.trim()))),
+        };
+        match key.as_str() {
+            "x" | "y" => {
+                let n = value
+                    .parse::<u32>()
+                    .map_err(|_| RleError::new(line_no, at, format!("invalid {} dimension '{}'", key, value)))?;
+                if n > limits.max_dimension {
+                    return Err(RleError::new(
+                        line_no,
+                        at,
+                        format!("{} = {} exceeds the limit of {}", key, n, limits.max_dimension),
+                    ));
+                }
+                if key == "x" {
+                    width = Some(n as i32);
+                } else {
+                    height = Some(n as i32);
+                }
+            }
+            "rule" => {
+                // The rule runs to the end of the line, since bounded-grid suffixes contain commas.
+                let value = &line[start..].split_once('=').unwrap().1;
+                rule = Some(parse_rule(value, line_no, at)?);
+                break;
+            }
+            _ => {}
+        }
+    }
+    match (width, height) {
+        (Some(w), Some(h)) => Ok((w, h, rule)),
+        (None, _) => Err(RleError::new(line_no, 1, "header is missing 'x = <width>'")),
+        (_, None) => Err(RleError::new(line_no, 1, "header is missing 'y = <height>'")),
+    }
+}
+
+/// Parse an RLE pattern.
+///
+/// Cells are stored relative to the top-left corner with `y` increasing downwards. Two-state
+/// data uses `b` for dead and `o` (or any other lowercase letter) for alive; multi-state data
+/// uses `.` for 0, `A`..`X` for 1..24 and a prefix letter `p`..`y` selecting further blocks of
+/// 24, so `pA` is 25 and `yO` is 255. Anything after the terminating `!` is ignored.
+pub fn parse(text: &str, limits: &RleLimits) -> Result<Pattern, RleError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+    let mut comments = vec![];
+    let mut rule = None;
+    let mut generation = 0;
+
+    let (width, height, header_rule) = loop {
+        match lines.next() {
+            Some((_, "")) => continue,
+            Some((line_no, line)) if line.starts_with('#') => {
+                if let Some(fields) = line.strip_prefix("#CXRLE") {
+                    generation = parse_extended(fields, line_no)?.unwrap_or(generation);
+                } else if ["#N", "#O", "#C", "#c"].iter().any(|tag| line.starts_with(tag)) {
+                    comments.push(line.to_string());
+                } else if let Some(r) = line.strip_prefix("#r") {
+                    rule = Some(parse_rule(r, line_no, 1)?);
+                }
+            }
+            Some((line_no, line)) => break parse_header(line, line_no, limits)?,
+            None => return Err(RleError::new(1, 1, "missing 'x = <width>, y = <height>' header line")),
+        }
+    };
+    let rule = header_rule.or(rule);
+
+    let mut cells = vec![];
+    let (mut x, mut y) = (0i64, 0i64);
+    let mut terminated = false;
+    // A pending run count and the position it started at; counts may wrap onto the next line.
+    let mut count: Option<(u64, usize, usize)> = None;
+    'lines: for (line_no, line) in lines {
+        if line.starts_with('#') {
+            continue;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let column = i + 1;
+            let c = chars[i];
+            i += 1;
+            if let Some(digit) = c.to_digit(10) {
+                let (n, start_line, start) = count.unwrap_or((0, line_no, column));
+                let n = n * 10 + digit as u64;
+                if n > limits.max_dimension as u64 {
+                    return Err(RleError::new(
+                        start_line,
+                        start,
+                        format!("run length exceeds the limit of {}", limits.max_dimension),
+                    ));
+                }
+                count = Some((n, start_line, start));
+                continue;
+            }
+            let run = count.take().map_or(1, |(n, _, _)| n as i64);
+            let state = match c {
+                ' ' | '\t' => continue,
+                '!' => {
+                    terminated = true;
+                    break 'lines;
+                }
+                '$' => {
+                    x = 0;
+                    y += run;
+                    continue;
+                }
+                'b' | '.' => 0,
+                'A'..='X' => (c as u8 - b'A') as usize + 1,
+                'p'..='y' if matches!(chars.get(i), Some('A'..='X')) => {
+                    let offset = (chars[i] as u8 - b'A') as usize;
+                    i += 1;
+                    (c as u8 - b'p' + 1) as usize * 24 + offset + 1
+                }
+                'a'..='z' => 1,
+                _ => return Err(RleError::new(line_no, column, format!("unexpected character '{}'", c))),
+            };
+            if state > 0 {
+                if x + run > width as i64 || y >= height as i64 {
+                    return Err(RleError::new(
+                        line_no,
+                        column,
+                        format!("cell ({}, {}) lies outside the declared {}x{} pattern", x + run - 1, y, width, height),
+                    ));
+                }
+                if cells.len() + run as usize > limits.max_cells {
+                    return Err(RleError::new(
+                        line_no,
+                        column,
+                        format!("pattern has more than {} cells", limits.max_cells),
+                    ));
+                }
+                cells.extend((x..x + run).map(|cx| (cx as i32, y as i32, state)));
+            }
+            x += run;
+        }
+    }
+    if !terminated {
+        let last_line = text.lines().count().max(1);
+        return Err(RleError::new(last_line, 1, "pattern data is not terminated by '!'"));
+    }
+
+    Ok(Pattern::new(cells, width, height, rule, comments, generation))
+}
+
+/// Encode a state as an RLE cell token.
+pub fn format_state(state: usize, multi_state: bool) -> String {
+    match (state, multi_state) {
+        (0, false) => "b".to_string(),
+        (_, false) => "o".to_string(),
+        (0, true) => ".".to_string(),
+        (s, true) if s <= 24 => char::from(b'A' + (s - 1) as u8).to_string(),
+        (s, true) => {
+            let (block, offset) = ((s - 1) / 24, (s - 1) % 24);
+            format!(
+                "{}{}",
+                char::from(b'p' + (block - 1) as u8),
+                char::from(b'A' + offset as u8)
+            )
+        }
+    }
+}
+
+/// Maximum line length of the pattern body, as recommended by the RLE format.
+const LINE_WIDTH: usize = 70;
+
+/// Accumulates run-length tokens, wrapping lines before they exceed `LINE_WIDTH`.
+struct RleWriter {
+    out: String,
+    line_len: usize,
+}
+
+impl RleWriter {
+    fn push(&mut self, count: usize, token: &str) {
+        let token = if count > 1 {
+            format!("{}{}", count, token)
+        } else {
+            token.to_string()
+        };
+        if self.line_len + token.len() > LINE_WIDTH {
+            self.out.push('\n');
+            self.line_len = 0;
+        }
+        self.line_len += token.len();
+        self.out.push_str(&token);
+    }
+}
+
+/// Write live cells as RLE.
+///
+/// `cells` holds `(x, y, state)` relative to the pattern's top-left corner and must lie inside
+/// `width` x `height`. Comment lines are copied ahead of the header, with plaintext `!` comments
+/// rewritten as `#C`, followed by a `#CXRLE Gen=` line when `generation` is non-zero.
+pub fn write(
+    cells: &[(i64, i64, usize)],
+    width: i64,
+    height: i64,
+    rule: &str,
+    multi_state: bool,
+    comments: &[String],
+    generation: u128,
+) -> String {
+    let mut header = String::new();
+    for comment in comments {
+        match comment.strip_prefix('!') {
+            Some(text) => header.push_str(&format!("#C {}", text)),
+            None => header.push_str(comment),
+        }
+        header.push('\n');
+    }
+    if generation > 0 {
+        header.push_str(&format!("#CXRLE Gen={}\n", generation));
+    }
+    header.push_str(&format!("x = {}, y = {}, rule = {}\n", width, height, rule));
+
+    let mut cells = cells.to_vec();
+    cells.sort_by_key(|&(x, y, _)| (y, x));
+
+    let mut writer = RleWriter {
+        out: header,
+        line_len: 0,
+    };
+    // Runs of equal cells are flushed lazily so that trailing dead cells never get written.
+    let (mut x, mut y) = (0, 0);
+    let mut run: Option<(usize, usize)> = None;
+    for (cx, cy, state) in cells {
+        if cy > y {
+            if let Some((count, s)) = run.take() {
+                writer.push(count, &format_state(s, multi_state));
+            }
+            writer.push((cy - y) as usize, "$");
+            x = 0;
+            y = cy;
+        }
+        let gap = (cx - x) as usize;
+        match run {
+            Some((count, s)) if s == state && gap == 0 => run = Some((count + 1, s)),
+            _ => {
+                if let Some((count, s)) = run.take() {
+                    writer.push(count, &format_state(s, multi_state));
+                }
+                if gap > 0 {
+                    writer.push(gap, &format_state(0, multi_state));
+                }
+                run = Some((1, state));
+            }
+        }
+        x = cx + 1;
+    }
+    if let Some((count, s)) = run {
+        writer.push(count, &format_state(s, multi_state));
+    }
+    writer.push(1, "!");
+    writer.out.push('\n');
+    writer.out
+}
@@ -0,0 +1,222 @@
+//! Finding copies of a target pattern in a universe.
+
+use crate::cell_rect;
+use crate::pattern::Pattern;
+use crate::rle::{RleError, RleLimits};
+use crate::view::BoundingBox;
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+/// What to look for and where.
+///
+/// The target's live cells must match exactly, and the dead cells inside its `width` x
+/// `height` frame must be dead unless they are marked "don't care". The "must be dead" mask can
+/// extend past the frame, for example to require empty space around the target. Masks use the
+/// target's coordinates: any non-dead cell of a mask pattern marks that position.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Search {
+    target: Pattern,
+    must_be_dead: Vec<(i32, i32)>,
+    dont_care: Vec<(i32, i32)>,
+    region: Option<(i64, i64, i64, i64)>,
+    all_orientations: bool,
+}
+
+/// A match: where the target's frame has its top-left corner, and in which orientation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub x: i64,
+    pub y: i64,
+    /// Bit 2 transposes the target (swapping x and y), then bit 0 mirrors it left-to-right
+    /// and bit 1 top-to-bottom; 0 is the target as given.
+    pub orientation: u8,
+}
+
+/// One orientation of the target, as offsets from its frame's top-left corner.
+struct Variant {
+    orientation: u8,
+    width: i64,
+    height: i64,
+    live: Vec<(i64, i64, usize)>,
+    dead: Vec<(i64, i64)>,
+}
+
+impl Search {
+    pub fn new(target: Pattern) -> Search {
+        Search {
+            target,
+            must_be_dead: vec![],
+            dont_care: vec![],
+            region: None,
+            all_orientations: false,
+        }
+    }
+
+    /// Every distinct orientation to try; symmetric targets yield fewer than eight.
+    fn variants(&self) -> Vec<Variant> {
+        let (width, height) = (self.target.width() as i64, self.target.height() as i64);
+        let live: HashMap<(i64, i64), usize> = self
+            .target
+            .cells()
+            .iter()
+            .map(|&(x, y, state)| ((x as i64, y as i64), state))
+            .collect();
+        let dont_care: HashSet<(i64, i64)> =
+            self.dont_care.iter().map(|&(x, y)| (x as i64, y as i64)).collect();
+        let mut dead: HashSet<(i64, i64)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|cell| !dont_care.contains(cell))
+            .collect();
+        dead.extend(self.must_be_dead.iter().map(|&(x, y)| (x as i64, y as i64)));
+        dead.retain(|cell| !live.contains_key(cell));
+
+        let orientations = if self.all_orientations { 0..8 } else { 0..1 };
+        let mut variants: Vec<Variant> = vec![];
+        for orientation in orientations {
+            let transform = |(x, y): (i64, i64)| {
+                let (x, y) = if orientation & 4 != 0 { (y, x) } else { (x, y) };
+                let (w, h) = if orientation & 4 != 0 { (height, width) } else { (width, height) };
+                let x = if orientation & 1 != 0 { w - 1 - x } else { x };
+                let y = if orientation & 2 != 0 { h - 1 - y } else { y };
+                (x, y)
+            };
+            let mut variant_live: Vec<_> = live
+                .iter()
+                .map(|(&cell, &state)| {
+                    let (x, y) = transform(cell);
+                    (x, y, state)
+                })
+                .collect();
+            let mut variant_dead: Vec<_> = dead.iter().map(|&cell| transform(cell)).collect();
+            variant_live.sort_unstable();
+            variant_dead.sort_unstable();
+            if variants.iter().any(|v| v.live == variant_live && v.dead == variant_dead) {
+                continue;
+            }
+            let (width, height) = if orientation & 4 != 0 { (height, width) } else { (width, height) };
+            variants.push(Variant {
+                orientation,
+                width,
+                height,
+                live: variant_live,
+                dead: variant_dead,
+            });
+        }
+        variants
+    }
+
+    /// Every match whose frame lies inside the inclusive rectangle `region`, given the
+    /// non-dead cells of that rectangle widened by `margin`.
+    pub fn run(
+        &self,
+        cells: &HashMap<(i64, i64), usize>,
+        region: (i64, i64, i64, i64),
+    ) -> Vec<SearchMatch> {
+        let (x0, y0, x1, y1) = region;
+        let mut matches = vec![];
+        for variant in self.variants() {
+            // Every match has some live cell of the universe under the target's first live cell.
+            let &(ax, ay, anchor_state) = match variant.live.first() {
+                Some(anchor) => anchor,
+                None => continue,
+            };
+            for (&(x, y), &state) in cells {
+                let (ox, oy) = (x - ax, y - ay);
+                if state != anchor_state
+                    || ox < x0
+                    || oy < y0
+                    || ox + variant.width - 1 > x1
+                    || oy + variant.height - 1 > y1
+                {
+                    continue;
+                }
+                let live_ok = variant
+                    .live
+                    .iter()
+                    .all(|&(dx, dy, s)| cells.get(&(ox + dx, oy + dy)) == Some(&s));
+                let dead_ok = live_ok
+                    && variant
+                        .dead
+                        .iter()
+                        .all(|&(dx, dy)| !cells.contains_key(&(ox + dx, oy + dy)));
+                if dead_ok {
+                    matches.push(SearchMatch {
+                        x: ox,
+                        y: oy,
+                        orientation: variant.orientation,
+                    });
+                }
+            }
+        }
+        matches.sort_by_key(|m| (m.y, m.x, m.orientation));
+        matches
+    }
+
+    /// How far past a match's frame the cells it depends on can lie, as
+    /// `(left, top, right, bottom)`.
+    pub fn margin(&self) -> (i64, i64, i64, i64) {
+        let mut margin = (0, 0, 0, 0);
+        for variant in self.variants() {
+            let cells = variant
+                .live
+                .iter()
+                .map(|&(x, y, _)| (x, y))
+                .chain(variant.dead.iter().copied());
+            for (x, y) in cells {
+                margin.0 = margin.0.max(-x);
+                margin.1 = margin.1.max(-y);
+                margin.2 = margin.2.max(x - (variant.width - 1));
+                margin.3 = margin.3.max(y - (variant.height - 1));
+            }
+        }
+        margin
+    }
+
+    /// The rectangle to search, as inclusive `(x0, y0, x1, y1)`: the one set with
+    /// `set_region`, or else every frame position that can overlap `bbox`.
+    pub fn region(&self, bbox: &BoundingBox) -> (i64, i64, i64, i64) {
+        self.region.unwrap_or_else(|| {
+            let side = self.target.width().max(self.target.height()) as i64;
+            let pad = (side - 1).max(0);
+            (bbox.min_x - pad, bbox.min_y - pad, bbox.max_x + pad, bbox.max_y + pad)
+        })
+    }
+}
+
+fn mask_cells(mask: &Pattern) -> Vec<(i32, i32)> {
+    mask.cells().iter().map(|&(x, y, _)| (x, y)).collect()
+}
+
+#[wasm_bindgen]
+impl Search {
+    /// Search for the pattern given as RLE.
+    pub fn from_rle(text: &str) -> Result<Search, RleError> {
+        Ok(Search::new(Pattern::parse_rle(text, &RleLimits::default())?))
+    }
+
+    pub fn from_pattern(target: &Pattern) -> Search {
+        Search::new(target.clone())
+    }
+
+    /// Cells, in the target's coordinates, that must be dead for a match.
+    pub fn set_must_be_dead(&mut self, mask: &Pattern) {
+        self.must_be_dead = mask_cells(mask);
+    }
+
+    /// Cells inside the target's frame whose state does not matter.
+    pub fn set_dont_care(&mut self, mask: &Pattern) {
+        self.dont_care = mask_cells(mask);
+    }
+
+    /// Only report matches whose frame lies inside the inclusive rectangle (x0, y0)-(x1, y1).
+    pub fn set_region(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) {
+        let area = cell_rect(x0, y0, x1, y1);
+        self.region = Some((area.min_x, area.min_y, area.max_x, area.max_y));
+    }
+
+    /// Also look for the target rotated and reflected.
+    pub fn set_all_orientations(&mut self, all: bool) {
+        self.all_orientations = all;
+    }
+}
@@ -0,0 +1,108 @@
+//! Random soups: seeded random fills of a rectangle, optionally symmetric.
+
+use crate::transform::Transform;
+use wasm_bindgen::prelude::*;
+
+/// Largest rectangle, in cells, that a soup may cover; every cell draws a random number.
+pub const MAX_SOUP_CELLS: i64 = 1 << 24;
+
+/// Symmetry imposed on a soup, about the centre of its rectangle.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// No symmetry.
+    C1 = 0,
+    /// Unchanged by a half turn.
+    C2 = 1,
+    /// Unchanged by a quarter turn; the rectangle must be square.
+    C4 = 2,
+    /// Mirrored left to right.
+    D2 = 3,
+    /// Mirrored left to right and top to bottom.
+    D4 = 4,
+    /// Unchanged by every rotation and reflection; the rectangle must be square.
+    D8 = 5,
+}
+
+impl Symmetry {
+    /// The transforms the soup must be unchanged by, besides the identity.
+    fn transforms(self) -> &'static [Transform] {
+        use Transform::*;
+        match self {
+            Symmetry::C1 => &[],
+            Symmetry::C2 => &[Rotate180],
+            Symmetry::C4 => &[Rotate90, Rotate180, Rotate270],
+            Symmetry::D2 => &[FlipHorizontal],
+            Symmetry::D4 => &[FlipHorizontal, FlipVertical, Rotate180],
+            Symmetry::D8 => &[
+                Rotate90,
+                Rotate180,
+                Rotate270,
+                FlipHorizontal,
+                FlipVertical,
+                FlipDiagonal,
+                FlipAntiDiagonal,
+            ],
+        }
+    }
+}
+
+/// SplitMix64, chosen because it is tiny and gives the same sequence on every platform.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform number in [0, 1) with 53 random bits.
+    fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Live cells of a `width` x `height` soup, relative to its top-left corner.
+///
+/// Cells are visited in rows from the top, and each one that is the first of its symmetry
+/// orbit draws one number, so a given seed always gives the same soup.
+pub fn generate(
+    width: i64,
+    height: i64,
+    density: f64,
+    seed: u64,
+    symmetry: Symmetry,
+) -> Result<Vec<(i64, i64)>, String> {
+    if !(0.0..=1.0).contains(&density) {
+        return Err(format!("density {} is not between 0 and 1", density));
+    }
+    if width.saturating_mul(height) > MAX_SOUP_CELLS {
+        return Err(format!("a {}x{} soup is too large", width, height));
+    }
+    if matches!(symmetry, Symmetry::C4 | Symmetry::D8) && width != height {
+        return Err(format!("{:?} symmetry needs a square, not {}x{}", symmetry, width, height));
+    }
+    let mut rng = SplitMix64(seed);
+    let mut cells = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            let orbit = symmetry
+                .transforms()
+                .iter()
+                .map(|t| t.apply(x, y, width, height));
+            if orbit.clone().any(|(ox, oy)| (oy, ox) < (y, x)) {
+                continue;
+            }
+            if rng.next_f64() < density {
+                cells.push((x, y));
+                cells.extend(orbit.filter(|&cell| cell != (x, y)));
+            }
+        }
+    }
+    cells.sort_unstable();
+    cells.dedup();
+    Ok(cells)
+}
@@ -0,0 +1,82 @@
+//! Rotations and reflections of the grid.
+
+use crate::view::BoundingBox;
+use wasm_bindgen::prelude::*;
+
+/// One of the seven non-trivial symmetries of the square grid, described as seen on screen
+/// (`y` increasing downwards).
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transform {
+    /// A quarter turn clockwise.
+    Rotate90 = 0,
+    Rotate180 = 1,
+    /// A quarter turn anticlockwise.
+    Rotate270 = 2,
+    /// Mirror left to right.
+    FlipHorizontal = 3,
+    /// Mirror top to bottom.
+    FlipVertical = 4,
+    /// Mirror in the line through the top-left and bottom-right corners, swapping x and y.
+    FlipDiagonal = 5,
+    /// Mirror in the line through the top-right and bottom-left corners.
+    FlipAntiDiagonal = 6,
+}
+
+impl Transform {
+    /// The transform as a transpose (swapping x and y), then mirrors in x and in y.
+    fn parts(self) -> (bool, bool, bool) {
+        match self {
+            Transform::Rotate90 => (true, true, false),
+            Transform::Rotate180 => (false, true, true),
+            Transform::Rotate270 => (true, false, true),
+            Transform::FlipHorizontal => (false, true, false),
+            Transform::FlipVertical => (false, false, true),
+            Transform::FlipDiagonal => (true, false, false),
+            Transform::FlipAntiDiagonal => (true, true, true),
+        }
+    }
+
+    /// Width and height of a `width` x `height` rectangle once transformed.
+    pub fn size(self, width: i64, height: i64) -> (i64, i64) {
+        let (transpose, _, _) = self.parts();
+        if transpose {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Where the cell at (x, y) of a `width` x `height` rectangle ends up, with the transformed
+    /// rectangle again starting at (0, 0).
+    pub fn apply(self, x: i64, y: i64, width: i64, height: i64) -> (i64, i64) {
+        let (transpose, mirror_x, mirror_y) = self.parts();
+        let (x, y) = if transpose { (y, x) } else { (x, y) };
+        let (width, height) = self.size(width, height);
+        let x = if mirror_x { width - 1 - x } else { x };
+        let y = if mirror_y { height - 1 - y } else { y };
+        (x, y)
+    }
+
+    /// Top-left corner of the inclusive rectangle `(x0, y0, x1, y1)` inside a `dim`-wide
+    /// square, once the whole square is transformed.
+    pub fn corner(self, rect: (i64, i64, i64, i64), dim: i64) -> (i64, i64) {
+        let (ax, ay) = self.apply(rect.0, rect.1, dim, dim);
+        let (bx, by) = self.apply(rect.2, rect.3, dim, dim);
+        (ax.min(bx), ay.min(by))
+    }
+
+    /// The rectangle `area` turns into when transformed about its own centre, rounding towards
+    /// the top left when the centre falls between cells.
+    pub fn rect(self, area: &BoundingBox) -> BoundingBox {
+        let (width, height) = self.size(area.width(), area.height());
+        let min_x = area.min_x + (area.width() - width).div_euclid(2);
+        let min_y = area.min_y + (area.height() - height).div_euclid(2);
+        BoundingBox {
+            min_x,
+            min_y,
+            max_x: min_x + width - 1,
+            max_y: min_y + height - 1,
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Framing the live pattern for display.
+
+use wasm_bindgen::prelude::*;
+
+/// Fraction of the view left empty around a fitted pattern, split between both sides.
+const FIT_MARGIN: f64 = 0.1;
+
+/// Smallest view height produced by `fit`, in cells, matching the UI's zoom limit.
+const MIN_VIEW_CELLS: f64 = 10.0;
+
+/// Inclusive extent of the live cells, in absolute cell coordinates.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    #[wasm_bindgen(skip)]
+    pub min_x: i64,
+    #[wasm_bindgen(skip)]
+    pub min_y: i64,
+    #[wasm_bindgen(skip)]
+    pub max_x: i64,
+    #[wasm_bindgen(skip)]
+    pub max_y: i64,
+}
+
+/// The corners as JS numbers, like every other coordinate handed to JS.
+#[wasm_bindgen]
+impl BoundingBox {
+    #[wasm_bindgen(getter = min_x)]
+    pub fn js_min_x(&self) -> f64 {
+        self.min_x as f64
+    }
+
+    #[wasm_bindgen(getter = min_y)]
+    pub fn js_min_y(&self) -> f64 {
+        self.min_y as f64
+    }
+
+    #[wasm_bindgen(getter = max_x)]
+    pub fn js_max_x(&self) -> f64 {
+        self.max_x as f64
+    }
+
+    #[wasm_bindgen(getter = max_y)]
+    pub fn js_max_y(&self) -> f64 {
+        self.max_y as f64
+    }
+}
+
+impl BoundingBox {
+    /// The rectangle with opposite corners (x0, y0) and (x1, y1), in either order.
+    pub fn from_corners(x0: i64, y0: i64, x1: i64, y1: i64) -> BoundingBox {
+        BoundingBox {
+            min_x: x0.min(x1),
+            min_y: y0.min(y1),
+            max_x: x0.max(x1),
+            max_y: y0.max(y1),
+        }
+    }
+
+    pub fn width(&self) -> i64 {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn height(&self) -> i64 {
+        self.max_y - self.min_y + 1
+    }
+
+    pub fn translated(&self, dx: i64, dy: i64) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x + dx,
+            min_y: self.min_y + dy,
+            max_x: self.max_x + dx,
+            max_y: self.max_y + dy,
+        }
+    }
+}
+
+/// A viewport in cell coordinates: its top-left corner and its size in cells.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// The smallest view with the given aspect ratio (width / height) that shows all of `bbox`
+/// with a margin, centred on it.
+pub fn fit(bbox: &BoundingBox, aspect: f64) -> View {
+    let (width, height) = (bbox.width() as f64, bbox.height() as f64);
+    let height = (height.max(width / aspect) * (1.0 + FIT_MARGIN)).max(MIN_VIEW_CELLS);
+    let width = height * aspect;
+    let center_x = bbox.min_x as f64 + bbox.width() as f64 / 2.0;
+    let center_y = bbox.min_y as f64 + bbox.height() as f64 / 2.0;
+    View {
+        x: center_x - width / 2.0,
+        y: center_y - height / 2.0,
+        width,
+        height,
+    }
+}
@@ -0,0 +1,114 @@
+//! Advancing by exact generation counts, far beyond the 32-bit coordinate range.
+
+mod common;
+
+use common::{cells, load, Cells, LIFE};
+use wasm_game_of_life::{PasteMode, Search, Transform, Universe};
+
+const GLIDER: &str = "x = 3, y = 3\nbo$2bo$3o!";
+const ACORN: &str = "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!";
+
+/// The glider after `n` generations: it moves one cell down and right every four.
+fn glider_after(n: u64) -> Cells {
+    let shift = (n / 4) as i64;
+    LIFE.run(&cells(&load(GLIDER)), n % 4)
+        .into_iter()
+        .map(|((x, y), state)| ((x + shift, y + shift), state))
+        .collect()
+}
+
+#[test]
+fn steps_exact_counts() {
+    for n in [0, 1, 3, 6, 7, 100, 0b1011011, 255, 1000] {
+        let mut uni = load(ACORN);
+        let start = cells(&uni);
+        uni.advance_by(n);
+        assert_eq!(cells(&uni), LIFE.run(&start, n), "after {}", n);
+        assert_eq!(uni.generation(), n.to_string());
+    }
+}
+
+#[test]
+fn steps_beyond_the_largest_power() {
+    // Bits 49 and 50 are above the largest single hashlife step, so they run as repeated steps.
+    for n in [(1 << 50) + (1 << 49) + 12345, (1 << 49) - 1] {
+        let mut uni = load(GLIDER);
+        uni.advance_by(n);
+        assert_eq!(cells(&uni), glider_after(n), "after {}", n);
+        assert_eq!(uni.generation(), n.to_string());
+    }
+}
+
+#[test]
+fn reaches_cells_beyond_i32() {
+    let mut uni = load(GLIDER);
+    uni.advance_by(1 << 34);
+    let far = (1i64 << 32) as f64;
+    let bbox = uni.bounding_box().unwrap();
+    assert_eq!((bbox.min_x, bbox.min_y), (1 << 32, 1 << 32));
+    assert_eq!(uni.get(far + 1.0, far), 1);
+    assert_eq!(uni.get(1.0, 0.0), 0);
+
+    uni.clear_cell(far + 1.0, far);
+    assert_eq!(uni.population(), 4);
+    uni.set(far - 5.0, far);
+    assert_eq!(uni.get(far - 5.0, far), 1);
+    assert_eq!(uni.bounding_box().unwrap().min_x, (1 << 32) - 5);
+}
+
+#[test]
+fn reads_cells_in_batches() {
+    let mut uni = load(GLIDER);
+    uni.advance_by(1 << 34);
+    let far = (1i64 << 32) as f64;
+    // Cells on the glider, beside it, far outside the root and at the origin it left.
+    let coords = [far + 1.0, far, far, far, -far, far, 1e15, -1e15, 1.0, 0.0];
+    assert_eq!(uni.get_cells(&coords), [1, 0, 0, 0, 0]);
+    assert_eq!(uni.get_cells(&coords), coords.chunks(2).map(|xy| uni.get(xy[0], xy[1])).collect::<Vec<_>>());
+    // A trailing unpaired value is ignored.
+    assert_eq!(uni.get_cells(&[far + 1.0, far, far + 2.0]), [1]);
+    assert!(uni.get_cells(&[]).is_empty());
+}
+
+#[test]
+fn rectangles_reach_beyond_i32() {
+    let far = 1e10;
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.paste_rle(GLIDER, far, -far, PasteMode::Or).unwrap();
+    assert_eq!(uni.get(far + 1.0, -far), 1);
+    assert!(uni.root_x() <= far && uni.root_x() > far - 64.0);
+    assert_eq!(uni.bounding_box().unwrap().js_min_y(), -far);
+
+    let copy = uni.copy_rect(far, -far, far + 2.0, -far + 2.0);
+    assert_eq!(copy.population(), 5);
+    let rle = uni.to_rle_rect(far, -far, far + 2.0, -far + 2.0);
+    assert!(rle.contains("x = 3, y = 3") && rle.ends_with("bo$2bo$3o!\n"), "{}", rle);
+
+    let mut search = Search::from_rle(GLIDER).unwrap();
+    search.set_region(far - 10.0, -far - 10.0, far + 10.0, -far + 10.0);
+    assert_eq!(uni.find_matches(&search).len(), 1);
+
+    uni.translate_rect(far, -far, far + 2.0, -far + 2.0, 5.0, 0.0);
+    uni.transform_rect(far + 5.0, -far, far + 7.0, -far + 2.0, Transform::FlipHorizontal);
+    assert_eq!(uni.get(far + 6.0, -far), 1);
+    uni.clear_rect(far, -far, far + 10.0, -far + 10.0);
+    assert_eq!(uni.population(), 0);
+}
+
+#[test]
+fn counts_generations() {
+    let mut uni = load(GLIDER);
+    uni.advance_pow2(40).unwrap();
+    uni.advance_by(3);
+    uni.advance_pow2(0).unwrap();
+    assert_eq!(uni.generation(), ((1u64 << 40) + 4).to_string());
+    assert!(uni.advance_pow2(64).is_err());
+    assert_eq!(uni.generation(), ((1u64 << 40) + 4).to_string());
+
+    uni.advance_pow2(50).unwrap();
+    assert_eq!(uni.generation(), ((1u64 << 50) + (1 << 40) + 4).to_string());
+
+    assert!(uni.undo());
+    assert!(uni.undo());
+    assert_eq!(uni.generation(), ((1u64 << 40) + 3).to_string());
+}
@@ -0,0 +1,33 @@
+//! Evolution timings over patterns from `www/dist/patterns`. Ignored by default; run with
+//! `cargo test --release --test bench -- --ignored --nocapture`.
+
+mod common;
+
+use common::load_file;
+use std::time::Instant;
+
+#[test]
+#[ignore]
+fn evolve() {
+    for (name, steps, j) in [
+        ("acorn.rle", 60, 3),
+        ("gosperglidergun.rle", 40, 4),
+        ("turingmachine.rle", 20, 6),
+    ] {
+        let mut uni = load_file(name);
+        uni.set_gc_threshold(usize::MAX);
+        let start = Instant::now();
+        for _ in 0..steps {
+            uni.advance_pow2(j).unwrap();
+        }
+        println!(
+            "{}: {} steps of 2^{} in {:?}, population {}, {} nodes",
+            name,
+            steps,
+            j,
+            start.elapsed(),
+            uni.population(),
+            uni.node_count()
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Plaintext `.cells` reading and writing, including a corpus run over every `.cells` file in
+//! `www/dist/patterns`.
+
+mod common;
+
+use common::{cells, Cells};
+use std::fs;
+use std::path::PathBuf;
+use wasm_game_of_life::{Pattern, Universe};
+
+const GLIDER: &str = "!Name: Glider\n!A small spaceship.\n.O.\n..O\nOOO\n";
+
+fn corpus() -> Vec<PathBuf> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/www/dist/patterns");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "cells"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn live(positions: &[(i64, i64)]) -> Cells {
+    positions.iter().map(|&pos| (pos, 1)).collect()
+}
+
+#[test]
+fn parses_patterns() {
+    let pattern = Pattern::from_cells(GLIDER).unwrap();
+    assert_eq!(pattern.cells(), &[(1, 0, 1), (2, 1, 1), (0, 2, 1), (1, 2, 1), (2, 2, 1)]);
+    assert_eq!((pattern.width(), pattern.height()), (3, 3));
+    assert_eq!(pattern.comments(), &["!Name: Glider", "!A small spaceship."]);
+    assert_eq!(pattern.rule(), None);
+
+    // `*` and `o` also mean alive, and trailing dead cells and empty rows still count.
+    let pattern = Pattern::from_cells("*.....\n\no\n").unwrap();
+    assert_eq!(pattern.cells(), &[(0, 0, 1), (0, 2, 1)]);
+    assert_eq!((pattern.width(), pattern.height()), (6, 3));
+}
+
+#[test]
+fn rejects_unknown_characters() {
+    let err = Pattern::from_cells("!Name: typo\n.O.\n.0.\n").unwrap_err();
+    assert!(err.contains("line 3, column 2"), "{}", err);
+}
+
+#[test]
+fn loads_at_an_offset() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set(0.0, 0.0);
+    uni.from_cells(GLIDER, -10.0, 7.0).unwrap();
+    assert_eq!(cells(&uni), live(&[(-9, 7), (-8, 8), (-10, 9), (-9, 9), (-8, 9)]));
+    // Fractional coordinates round down to the cell they fall in.
+    uni.from_cells(GLIDER, -0.5, 2.5).unwrap();
+    assert_eq!(cells(&uni), live(&[(0, 2), (1, 3), (-1, 4), (0, 4), (1, 4)]));
+    assert!(uni.from_cells("O?", 0.0, 0.0).is_err());
+    assert_eq!(uni.population(), 5);
+}
+
+#[test]
+fn writes_rectangles() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.from_cells(GLIDER, 4.0, 4.0).unwrap();
+    // Trailing dead cells are dropped.
+    assert_eq!(uni.to_cells(4.0, 4.0, 6.0, 6.0), "!Name: Glider\n!A small spaceship.\n.O\n..O\nOOO\n");
+    // Corners may come in either order, cells outside are clipped and empty rows stay as a dot.
+    assert_eq!(uni.to_cells(6.0, 7.0, 3.0, 4.0), "!Name: Glider\n!A small spaceship.\n..O\n...O\n.OOO\n.\n");
+    assert_eq!(uni.to_cells(5.0, 4.0, 5.0, 5.0), "!Name: Glider\n!A small spaceship.\nO\n.\n");
+}
+
+#[test]
+fn comments_round_trip() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.from_cells(GLIDER, 0.0, 0.0).unwrap();
+    let written = uni.to_cells(0.0, 0.0, 2.0, 2.0);
+    let reread = Pattern::from_cells(&written).unwrap();
+    assert_eq!(reread.comments(), Pattern::from_cells(GLIDER).unwrap().comments());
+
+    // RLE comments lose their tag but keep their text.
+    uni.from_rle("#N Glider\n#C A small spaceship.\nx = 3, y = 3\nbo$2bo$3o!".to_string()).unwrap();
+    let written = uni.to_cells(0.0, 0.0, 2.0, 2.0);
+    assert!(written.starts_with("! Glider\n! A small spaceship.\n"), "{}", written);
+}
+
+#[test]
+fn corpus_parses_and_round_trips() {
+    let paths = corpus();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let text = String::from_utf8_lossy(&fs::read(&path).unwrap()).into_owned();
+        let pattern = Pattern::from_cells(&text).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let mut uni = Universe::new(0.0, 0.0);
+        uni.from_cells(&text, 0.0, 0.0).unwrap();
+        assert_eq!(uni.population(), pattern.population(), "{}", path.display());
+
+        let (width, height) = (pattern.width().max(1) as f64, pattern.height().max(1) as f64);
+        let written = uni.to_cells(0.0, 0.0, width - 1.0, height - 1.0);
+        let reparsed = Pattern::from_cells(&written)
+            .unwrap_or_else(|e| panic!("{} (rewritten): {}", path.display(), e));
+        assert_eq!(pattern.cells(), reparsed.cells(), "{}", path.display());
+        assert_eq!(pattern.comments(), reparsed.comments(), "{}", path.display());
+        assert_eq!(pattern.height().max(1), reparsed.height(), "{}", path.display());
+    }
+}
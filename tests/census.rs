@@ -0,0 +1,86 @@
+//! Object census: splitting a universe into objects, classifying and naming them.
+
+mod common;
+
+use common::cells;
+use wasm_game_of_life::{PeriodKind, Pattern, Universe};
+
+fn place(uni: &mut Universe, rle: &str, x: i32, y: i32) {
+    let pattern = Pattern::from_rle(&format!("x = 8, y = 8\n{}", rle)).unwrap();
+    for &(cx, cy, state) in pattern.cells() {
+        uni.set_state((x + cx) as f64, (y + cy) as f64, state).unwrap();
+    }
+}
+
+fn garden() -> Universe {
+    let mut uni = Universe::new(0.0, 0.0);
+    place(&mut uni, "2o$2o!", 0, 0);
+    place(&mut uni, "obo$b2o$bo!", 10, 0);
+    place(&mut uni, "o$o$o!", 20, 0);
+    place(&mut uni, "bo$obo$obo$bo!", 30, 0);
+    place(&mut uni, "2o$2o!", 40, 0);
+    place(&mut uni, "2o$2o!", 50, -3);
+    place(&mut uni, "bo2bo$o$o3bo$4o!", 0, 20);
+    place(&mut uni, "2ob2o$2ob2o!", 20, 20);
+    place(&mut uni, "b2o$2o$bo!", 40, 20);
+    uni
+}
+
+#[test]
+fn names_common_objects() {
+    let census = garden().census(64);
+    assert_eq!(census.len(), 9);
+    let counts = census.counts();
+    assert_eq!(counts[0], ("block".to_string(), 3));
+    let mut rest = counts[1..].to_vec();
+    rest.sort();
+    let expected: Vec<(String, usize)> = ["8-cell still life", "LWSS", "beehive", "blinker", "glider", "unknown"]
+        .iter()
+        .map(|name| (name.to_string(), 1))
+        .collect();
+    assert_eq!(rest, expected);
+}
+
+#[test]
+fn classifies_and_locates_objects() {
+    let census = garden().census(64);
+    let glider = census.objects().iter().find(|o| o.name == "glider").unwrap();
+    assert_eq!((glider.x, glider.y, glider.width, glider.height), (10, 0, 3, 3));
+    assert_eq!(glider.periodicity.kind, PeriodKind::Spaceship);
+    assert_eq!(glider.population, 5);
+
+    let blinker = census.objects().iter().find(|o| o.name == "blinker").unwrap();
+    assert_eq!((blinker.periodicity.kind, blinker.periodicity.period), (PeriodKind::Oscillator, 2));
+
+    let first = &census.objects()[0];
+    assert_eq!((first.name.as_str(), first.x, first.y), ("block", 50, -3));
+}
+
+#[test]
+fn looks_objects_up_by_index() {
+    let census = garden().census(64);
+    assert_eq!(census.name(0).as_deref(), Some("block"));
+    assert_eq!(census.kind(0), Some(PeriodKind::StillLife));
+    assert_eq!(census.period(0), Some(1));
+    // Past the end there is nothing to report, rather than a panic.
+    assert_eq!(census.name(9), None);
+    assert_eq!(census.kind(9), None);
+    assert_eq!(census.period(usize::MAX), None);
+}
+
+#[test]
+fn summarises_counts() {
+    let census = garden().census(64);
+    assert!(census.summary().starts_with("3 block\n"));
+    assert_eq!(census.summary().lines().count(), 7);
+}
+
+#[test]
+fn leaves_the_universe_untouched() {
+    let uni = garden();
+    let before = cells(&uni);
+    uni.census(64);
+    assert_eq!(cells(&uni), before);
+    assert_eq!(uni.generation(), "0");
+    assert!(Universe::new(0.0, 0.0).census(64).is_empty());
+}
@@ -0,0 +1,127 @@
+//! Helpers shared by the integration tests: a brute-force reference evolution to check hashlife
+//! against, and a dump of every cell in a universe.
+
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use wasm_game_of_life::Universe;
+
+/// Non-zero cells by position.
+pub type Cells = BTreeMap<(i64, i64), usize>;
+
+/// A Generations rule evolved one cell at a time, independent of the crate's own rule code.
+pub struct Reference {
+    pub birth: &'static [u32],
+    pub survival: &'static [u32],
+    pub states: usize,
+}
+
+/// Conway's Game of Life, B3/S23.
+pub const LIFE: Reference = Reference {
+    birth: &[3],
+    survival: &[2, 3],
+    states: 2,
+};
+
+/// Brian's Brain, B2/S/C3.
+pub const BRIANS_BRAIN: Reference = Reference {
+    birth: &[2],
+    survival: &[],
+    states: 3,
+};
+
+impl Reference {
+    /// One generation of `cells`.
+    pub fn step(&self, cells: &Cells) -> Cells {
+        let mut neighbours: BTreeMap<(i64, i64), u32> = BTreeMap::new();
+        for (&(x, y), _) in cells.iter().filter(|(_, &state)| state == 1) {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if (dx, dy) != (0, 0) {
+                        *neighbours.entry((x + dx, y + dy)).or_default() += 1;
+                    }
+                }
+            }
+        }
+        let mut next = Cells::new();
+        for (&pos, &state) in cells {
+            let count = neighbours.get(&pos).copied().unwrap_or(0);
+            let state = match state {
+                1 if self.survival.contains(&count) => 1,
+                _ if state + 1 < self.states => state + 1,
+                _ => 0,
+            };
+            if state != 0 {
+                next.insert(pos, state);
+            }
+        }
+        for (&pos, &count) in &neighbours {
+            if !cells.contains_key(&pos) && self.birth.contains(&count) {
+                next.insert(pos, 1);
+            }
+        }
+        next
+    }
+
+    /// `n` generations of `cells`.
+    pub fn run(&self, cells: &Cells, n: u64) -> Cells {
+        (0..n).fold(cells.clone(), |cells, _| self.step(&cells))
+    }
+}
+
+/// A universe holding the RLE pattern `rle`.
+pub fn load(rle: &str) -> Universe {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.from_rle(rle.to_string()).unwrap();
+    uni
+}
+
+/// A universe holding the pattern file `name` from `www/dist/patterns`.
+pub fn load_file(name: &str) -> Universe {
+    let path = format!("{}/www/dist/patterns/{}", env!("CARGO_MANIFEST_DIR"), name);
+    load(&String::from_utf8_lossy(&std::fs::read(path).unwrap()))
+}
+
+/// Every non-zero cell of `uni`.
+pub fn cells(uni: &Universe) -> Cells {
+    let mut cells = Cells::new();
+    if let Some(bbox) = uni.bounding_box() {
+        for y in bbox.min_y..=bbox.max_y {
+            for x in bbox.min_x..=bbox.max_x {
+                let state = uni.get(x as f64, y as f64);
+                if state != 0 {
+                    cells.insert((x, y), state);
+                }
+            }
+        }
+    }
+    cells
+}
+
+/// A small linear congruential generator, so that randomised tests are the same on every run.
+pub struct Lcg(pub u64);
+
+impl Lcg {
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
+
+    /// A number in `min..min + n`.
+    pub fn within(&mut self, min: i64, n: u64) -> i64 {
+        min + self.below(n) as i64
+    }
+}
+
+/// A universe with `count` cells scattered over the `width` x `height` rectangle at (x, y),
+/// each in a random state from 1 to `max_state`.
+pub fn scatter(rng: &mut Lcg, x: i64, y: i64, width: u64, height: u64, count: usize, max_state: u64) -> Universe {
+    let mut uni = Universe::new(0.0, 0.0);
+    for _ in 0..count {
+        let (cx, cy) = (rng.within(x, width), rng.within(y, height));
+        let state = 1 + rng.below(max_state) as usize;
+        uni.set_state(cx as f64, cy as f64, state).unwrap();
+    }
+    uni
+}
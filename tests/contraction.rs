@@ -0,0 +1,61 @@
+//! The root shrinks back to the live pattern after advances and edits.
+
+mod common;
+
+use common::{cells, load, LIFE};
+use wasm_game_of_life::Universe;
+
+#[test]
+fn root_stays_small_as_a_glider_travels() {
+    let mut uni = load("x = 3, y = 3\nbo$2bo$3o!");
+    let start = cells(&uni);
+    uni.advance_pow2(40).unwrap();
+    assert_eq!(uni.root_level(), 3);
+    let shift = 1i64 << 38;
+    let expected = start.iter().map(|(&(x, y), &state)| ((x + shift, y + shift), state)).collect();
+    assert_eq!(cells(&uni), expected);
+    let root_x = uni.root_x() as i64;
+    assert!(root_x <= shift && root_x + 8 > shift + 2);
+}
+
+#[test]
+fn clearing_far_cells_shrinks_the_root() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set(0.0, 0.0);
+    uni.set(1048576.0, 1048576.0);
+    assert!(uni.root_level() > 20);
+    uni.clear_cell(1048576.0, 1048576.0);
+    assert_eq!(uni.root_level(), 3);
+    assert_eq!(uni.get(0.0, 0.0), 1);
+    assert_eq!(uni.population(), 1);
+}
+
+#[test]
+fn dead_pattern_shrinks_to_minimum() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set(0.0, 0.0);
+    uni.set(1000.0, -1000.0);
+    uni.advance_by(1);
+    assert_eq!(uni.population(), 0);
+    assert_eq!(uni.root_level(), 3);
+}
+
+#[test]
+fn contraction_keeps_evolution_exact() {
+    // The block stays behind while the glider flies off; both must keep evolving exactly.
+    let mut uni = load("x = 3, y = 3\nbo$2bo$3o!");
+    uni.set(-200.0, -200.0);
+    uni.set(-199.0, -200.0);
+    uni.set(-200.0, -199.0);
+    uni.set(-199.0, -199.0);
+    let mut expected = cells(&uni);
+    for n in [1, 4, 16, 64, 256] {
+        uni.advance_by(n);
+        expected = LIFE.run(&expected, n);
+        assert_eq!(cells(&uni), expected);
+    }
+    for &(x, y) in &[(-200.0, -200.0), (-199.0, -200.0), (-200.0, -199.0), (-199.0, -199.0)] {
+        uni.clear_cell(x, y);
+    }
+    assert_eq!(uni.root_level(), 3);
+}
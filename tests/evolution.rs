@@ -0,0 +1,90 @@
+//! Hashlife evolution checked against a brute-force reference.
+
+mod common;
+
+use common::{cells, load, load_file, Reference, BRIANS_BRAIN, LIFE};
+use wasm_game_of_life::Universe;
+
+const R_PENTOMINO: &str = "x = 3, y = 3\nb2o$2ob$bo!";
+const ACORN: &str = "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!";
+
+/// Steps `uni` by each of `steps` in turn, comparing every result with the reference.
+fn check_life(mut uni: Universe, steps: &[u64]) {
+    let mut expected = cells(&uni);
+    for &n in steps {
+        uni.advance_by(n);
+        expected = LIFE.run(&expected, n);
+        assert_eq!(cells(&uni), expected, "after a step of {}", n);
+        assert_eq!(uni.population(), expected.len());
+    }
+}
+
+#[test]
+fn r_pentomino_matches_reference() {
+    check_life(load(R_PENTOMINO), &[1, 1, 2, 5, 16, 100, 200, 333, 500]);
+}
+
+#[test]
+fn gosper_gun_matches_reference() {
+    check_life(load_file("gosperglidergun.rle"), &[1, 3, 15, 30, 64, 120]);
+}
+
+#[test]
+fn brians_brain_matches_reference() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set_rule("B2/S/C3").unwrap();
+    let mut seed = 12345u64;
+    for y in 0..24 {
+        for x in 0..24 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let state = (seed >> 33) as usize % 5;
+            if state < 3 {
+                uni.set_state(x as f64, y as f64, state).unwrap();
+            }
+        }
+    }
+    let mut jumped = Universe::new(0.0, 0.0);
+    jumped.set_rule("B2/S/C3").unwrap();
+    jumped.union_with(&uni);
+
+    let mut expected = cells(&uni);
+    for generation in 1..=20 {
+        uni.advance_by(1);
+        expected = BRIANS_BRAIN.step(&expected);
+        assert_eq!(cells(&uni), expected, "generation {}", generation);
+        let mut counts = vec![0; 3];
+        for &state in expected.values() {
+            counts[state] += 1;
+        }
+        let mut populations = uni.state_populations();
+        populations.resize(3, 0);
+        assert_eq!(populations, counts, "generation {}", generation);
+    }
+    assert!(expected.values().any(|&state| state == 1));
+    jumped.advance_by(20);
+    assert_eq!(cells(&jumped), expected);
+}
+
+#[test]
+fn mixed_step_sizes_match_reference() {
+    check_life(load(ACORN), &[8, 32, 8, 1, 32, 16, 8, 64, 2, 8]);
+}
+
+#[test]
+fn rule_change_discards_cached_results() {
+    const HIGHLIFE: Reference = Reference {
+        birth: &[3, 6],
+        survival: &[2, 3],
+        states: 2,
+    };
+    let mut uni = load(ACORN);
+    let mut expected = cells(&uni);
+    for (rule, reference) in [("B3/S23", &LIFE), ("B36/S23", &HIGHLIFE), ("B3/S23", &LIFE)].iter() {
+        uni.set_rule(rule).unwrap();
+        for &n in &[8, 32, 8, 1] {
+            uni.advance_by(n);
+            expected = reference.run(&expected, n);
+            assert_eq!(cells(&uni), expected, "{} after a step of {}", rule, n);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Node garbage collection: collections must not change what the universe evolves into, nor
+//! lose the roots of pinned snapshots or of the undo history.
+
+use wasm_game_of_life::Universe;
+
+const ACORN: &str = "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!";
+
+fn acorn(gc_threshold: usize) -> Universe {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set_gc_threshold(gc_threshold);
+    uni.from_rle(ACORN.to_string()).unwrap();
+    uni
+}
+
+#[test]
+fn collections_preserve_evolution() {
+    let mut collected = acorn(500);
+    let mut reference = acorn(usize::MAX);
+    for n in [1, 7, 30, 64, 3, 200, 128] {
+        collected.advance_by(n);
+        reference.advance_by(n);
+        assert_eq!(collected.to_rle(), reference.to_rle(), "after {}", n);
+    }
+    assert_eq!(collected.population(), reference.population());
+    assert!(collected.node_count() < reference.node_count());
+
+    collected.collect_garbage();
+    collected.advance_by(100);
+    reference.advance_by(100);
+    assert_eq!(collected.to_rle(), reference.to_rle());
+}
+
+#[test]
+fn collections_keep_snapshots() {
+    let mut uni = acorn(300);
+    let start = uni.to_rle();
+    let pinned = uni.snapshot();
+    uni.advance_by(150);
+    let later = uni.to_rle();
+    let pinned_later = uni.snapshot();
+    for _ in 0..20 {
+        uni.advance_by(37);
+    }
+    uni.collect_garbage();
+
+    assert!(uni.restore_snapshot(pinned));
+    assert_eq!(uni.to_rle(), start);
+    assert!(uni.restore_snapshot(pinned_later));
+    assert_eq!(uni.to_rle(), later);
+    uni.advance_by(10);
+    let mut reference = acorn(usize::MAX);
+    reference.advance_by(160);
+    assert_eq!(uni.to_rle(), reference.to_rle());
+}
+
+#[test]
+fn collections_keep_undo_history() {
+    let mut uni = acorn(300);
+    let mut states = vec![uni.to_rle()];
+    for n in [50, 77, 120] {
+        uni.advance_by(n);
+        states.push(uni.to_rle());
+    }
+    uni.collect_garbage();
+    for state in states.iter().rev().skip(1) {
+        assert!(uni.undo());
+        assert_eq!(&uni.to_rle(), state);
+    }
+}
+
+#[test]
+fn restore_rejects_unknown_snapshot() {
+    let mut uni = acorn(usize::MAX);
+    assert!(!uni.restore_snapshot(0));
+    let pinned = uni.snapshot();
+    uni.advance_by(5);
+    let current = uni.to_rle();
+    assert!(!uni.restore_snapshot(pinned + 1));
+    uni.clear_snapshots();
+    assert!(!uni.restore_snapshot(pinned));
+    assert_eq!(uni.to_rle(), current);
+}
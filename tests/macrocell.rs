@@ -0,0 +1,101 @@
+//! Macrocell reading and writing.
+
+mod common;
+
+use common::{cells, load, load_file, Cells};
+use wasm_game_of_life::Universe;
+
+/// `cells` moved so that its bounding box starts at (0, 0); Macrocell has no origin, so a
+/// round trip keeps the shape but not the position.
+fn normalised(cells: &Cells) -> Cells {
+    let min_x = cells.keys().map(|&(x, _)| x).min().unwrap_or(0);
+    let min_y = cells.keys().map(|&(_, y)| y).min().unwrap_or(0);
+    cells.iter().map(|(&(x, y), &state)| ((x - min_x, y - min_y), state)).collect()
+}
+
+fn read(text: &str) -> Result<Universe, String> {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.from_macrocell(text)?;
+    Ok(uni)
+}
+
+#[test]
+fn two_state_round_trip() {
+    let mut uni = load_file("gosperglidergun.rle");
+    uni.advance_by(100);
+    let text = uni.to_macrocell();
+    assert!(text.starts_with("[M2]"));
+    assert!(text.lines().any(|line| line == "#G 100"), "{}", text);
+    assert!(text.lines().any(|line| line.starts_with(['.', '*', '$'])));
+
+    let copy = read(&text).unwrap();
+    assert_eq!(copy.generation(), "100");
+    assert_eq!(copy.rule(), "B3/S23");
+    assert_eq!(normalised(&cells(&copy)), normalised(&cells(&uni)));
+    assert_eq!(copy.to_macrocell(), text);
+}
+
+#[test]
+fn reads_multi_state_level_one_nodes() {
+    let uni = read("[M2] (test)\n#R B2/S/C3\n1 1 2 0 0\n2 1 0 0 1\n").unwrap();
+    assert_eq!(uni.rule(), "B2/S/C3");
+    let expected: Cells = [((-2, -2), 1), ((-1, -2), 2), ((0, 0), 1), ((1, 0), 2)].iter().copied().collect();
+    assert_eq!(cells(&uni), expected);
+
+    let copy = read(&uni.to_macrocell()).unwrap();
+    assert_eq!(normalised(&cells(&copy)), normalised(&expected));
+}
+
+#[test]
+fn writes_multi_state_patterns() {
+    let mut uni = load("x = 3, y = 2, rule = B2/S/C3\nA.B$BA!");
+    uni.advance_by(3);
+    let text = uni.to_macrocell();
+    assert!(text.lines().any(|line| line == "#R B2/S/C3"));
+    assert!(text.lines().skip(1).all(|line| !line.starts_with(['.', '*', '$'])), "{}", text);
+    let copy = read(&text).unwrap();
+    assert_eq!(copy.generation(), "3");
+    assert_eq!(normalised(&cells(&copy)), normalised(&cells(&uni)));
+}
+
+#[test]
+fn ignores_bounded_grid_suffix() {
+    let uni = read("[M2] (test)\n#R B36/S23:T100,100\n$.*$\n").unwrap();
+    assert_eq!(uni.rule(), "B36/S23");
+    assert_eq!(uni.population(), 1);
+}
+
+#[test]
+fn rejects_malformed_files() {
+    for text in [
+        "#R B3/S23\n$.*$\n",
+        "[M2] (test)\n#R B3/S23\n",
+        "[M2] (test)\n4 2 0 0 0\n",
+        "[M2] (test)\n$.*$\n5 1 0 0 0\n",
+        "[M2] (test)\n1 1 0 0 0\n3 1 0 0 0\n",
+        "[M2] (test)\n1 1 0 0 0\n2 0 1 0\n",
+        "[M2] (test)\n1 1 0 0 999\n",
+        "[M2] (test)\n$.*x$\n",
+        "[M2] (test)\n#G soon\n$.*$\n",
+        "[M2] (test)\n#R B0/S8\n$.*$\n",
+        "[M2]\n5000000 0 0 0 0\n",
+        "[M2]\n$$$$$$$.*$\n70 0 0 0 0\n",
+    ] {
+        assert!(read(text).is_err(), "{:?}", text);
+    }
+}
+
+#[test]
+fn accepts_the_deepest_level() {
+    let uni = read("[M2] (test)\n$$$$$$$.*$\n62 0 0 0 0\n").unwrap();
+    assert_eq!(uni.population(), 0);
+    assert!(read("[M2] (test)\n63 0 0 0 0\n").is_err());
+}
+
+#[test]
+fn keeps_universe_on_error() {
+    let mut uni = load("x = 3, y = 1\n3o!");
+    let before = cells(&uni);
+    assert!(uni.from_macrocell("[M2] (test)\n1 1 0 0 0\n2 7 0 0 0\n").is_err());
+    assert_eq!(cells(&uni), before);
+}
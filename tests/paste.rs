@@ -0,0 +1,112 @@
+//! Pasting patterns into a universe in each mode, from RLE and plaintext.
+
+mod common;
+
+use common::{cells, scatter, Cells, Lcg};
+use wasm_game_of_life::{PasteMode, Pattern, Transform, Universe};
+
+const MODES: [PasteMode; 4] = [PasteMode::Or, PasteMode::Xor, PasteMode::Copy, PasteMode::Erase];
+
+/// A random two-state pattern as RLE, and its live cells.
+fn random_rle(rng: &mut Lcg, width: i64, height: i64) -> (String, Vec<(i64, i64)>) {
+    let (mut rle, mut live) = (format!("x = {}, y = {}\n", width, height), Vec::new());
+    for y in 0..height {
+        for x in 0..width {
+            if rng.below(2) == 0 {
+                live.push((x, y));
+                rle.push('o');
+            } else {
+                rle.push('b');
+            }
+        }
+        rle.push('$');
+    }
+    rle.push('!');
+    (rle, live)
+}
+
+#[test]
+fn modes_match_cell_by_cell_reference() {
+    let mut rng = Lcg(9);
+    for round in 0..32 {
+        let mode = MODES[round % 4];
+        let mut uni = scatter(&mut rng, -20, -20, 40, 40, 300, 1);
+        let before = cells(&uni);
+        let (width, height) = (rng.within(1, 12), rng.within(1, 12));
+        let (rle, live) = random_rle(&mut rng, width, height);
+        let (px, py) = (rng.within(-25, 50), rng.within(-25, 50));
+        uni.paste_rle(&rle, px as f64, py as f64, mode).unwrap();
+
+        let mut expected = before.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let pos = (px + x, py + y);
+                let (old, new) = (before.contains_key(&pos), live.contains(&(x, y)));
+                let state = match mode {
+                    PasteMode::Or => old || new,
+                    PasteMode::Xor => old != new,
+                    PasteMode::Copy => new,
+                    PasteMode::Erase => old && !new,
+                };
+                if state {
+                    expected.insert(pos, 1);
+                } else {
+                    expected.remove(&pos);
+                }
+            }
+        }
+        assert_eq!(cells(&uni), expected, "{:?}", mode);
+        if expected != before {
+            assert!(uni.undo());
+            assert_eq!(cells(&uni), before);
+        }
+    }
+}
+
+#[test]
+fn pastes_plaintext_and_transformed_patterns() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.paste_cells("!Name: glider\n.O.\n..O\nOOO\n", 5.0, 5.0, PasteMode::Or).unwrap();
+    let glider: Cells = [(6, 5), (7, 6), (5, 7), (6, 7), (7, 7)].iter().map(|&pos| (pos, 1)).collect();
+    assert_eq!(cells(&uni), glider);
+
+    let turned = Pattern::from_rle("x = 3, y = 2\n3o$o!").unwrap().transformed(Transform::Rotate90);
+    assert_eq!((turned.width(), turned.height()), (2, 3));
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.paste(&turned, -1.0, -1.0, PasteMode::Or);
+    let expected: Cells = [(-1, -1), (0, -1), (0, 0), (0, 1)].iter().map(|&pos| (pos, 1)).collect();
+    assert_eq!(cells(&uni), expected);
+}
+
+#[test]
+fn bad_input_leaves_the_universe_alone() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set(0.0, 0.0);
+    assert!(uni.paste_rle("x = 2, y = 2\n2o$2?!", 0.0, 0.0, PasteMode::Copy).is_err());
+    assert_eq!(uni.population(), 1);
+    // Only the `set` is recorded.
+    assert!(uni.undo());
+    assert!(!uni.undo());
+}
+
+#[test]
+fn pasting_keeps_the_start_but_loading_replaces_it() {
+    let block: Cells = [(0, 0), (1, 0), (0, 1), (1, 1)].iter().map(|&pos| (pos, 1)).collect();
+
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.paste_rle("x = 2, y = 2\n2o$2o!", 0.0, 0.0, PasteMode::Or).unwrap();
+    uni.reset_to_start();
+    assert_eq!(uni.population(), 0);
+
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.paste_rle("x = 3, y = 1\n3o!", -1.0, 0.0, PasteMode::Or).unwrap();
+    uni.set(50.0, 50.0);
+    uni.from_rle("x = 2, y = 2\n2o$2o!".to_string()).unwrap();
+    assert_eq!(cells(&uni), block);
+    assert!(!uni.undo());
+    uni.advance_by(5);
+    uni.set(10.0, 10.0);
+    uni.reset_to_start();
+    assert_eq!(cells(&uni), block);
+    assert_eq!(uni.generation(), "0");
+}
@@ -0,0 +1,88 @@
+//! Period detection: still lifes, oscillators, spaceships, dying and non-periodic patterns.
+
+mod common;
+
+use common::{cells, load, load_file};
+use wasm_game_of_life::PeriodKind;
+
+#[test]
+fn finds_still_lifes() {
+    for rle in ["x = 2, y = 2\n2o$2o!", "x = 4, y = 3\nb2ob$o2bo$b2o!"] {
+        let p = load(rle).detect_period(10);
+        assert_eq!((p.kind, p.period, p.start), (PeriodKind::StillLife, 1, 0), "{}", rle);
+    }
+}
+
+#[test]
+fn finds_oscillator_periods() {
+    let p = load("x = 3, y = 1\n3o!").detect_period(10);
+    assert_eq!((p.kind, p.period, p.start), (PeriodKind::Oscillator, 2, 0));
+    for (name, period) in [("pulsar.rle", 3), ("pentadecathlon.rle", 15)] {
+        let p = load_file(name).detect_period(100);
+        assert_eq!((p.kind, p.period, p.dx, p.dy), (PeriodKind::Oscillator, period, 0, 0), "{}", name);
+    }
+}
+
+#[test]
+fn reports_when_the_cycle_starts() {
+    // Three cells of a block fill in to the block after one generation.
+    let p = load("x = 2, y = 2\n2o$o!").detect_period(10);
+    assert_eq!((p.kind, p.period, p.start), (PeriodKind::StillLife, 1, 1));
+}
+
+#[test]
+fn reports_dying_patterns() {
+    let p = load("x = 2, y = 1\n2o!").detect_period(10);
+    assert_eq!((p.kind, p.start), (PeriodKind::Empty, 1));
+    let p = load_file("diehard.rle").detect_period(200);
+    assert_eq!((p.kind, p.start), (PeriodKind::Empty, 130));
+}
+
+#[test]
+fn gives_up_after_max_period() {
+    let p = load_file("rpentomino.rle").detect_period(100);
+    assert_eq!((p.kind, p.period), (PeriodKind::NotPeriodic, 0));
+}
+
+#[test]
+fn leaves_the_universe_untouched() {
+    let mut uni = load_file("rpentomino.rle");
+    uni.advance_by(3);
+    let before = (cells(&uni), uni.generation(), uni.root_x(), uni.root_y());
+    uni.detect_period(50);
+    assert_eq!(before, (cells(&uni), uni.generation(), uni.root_x(), uni.root_y()));
+    assert!(uni.undo());
+    assert!(!uni.undo());
+}
+
+#[test]
+fn finds_spaceship_displacement() {
+    let p = load("x = 3, y = 3\nbo$2bo$3o!").detect_period(100);
+    assert_eq!((p.kind, p.period, p.dx, p.dy), (PeriodKind::Spaceship, 4, 1, 1));
+    assert_eq!(p.speed(), "c/4 diagonal");
+
+    let p = load_file("lwss.rle").detect_period(100);
+    assert_eq!((p.kind, p.period, p.dy), (PeriodKind::Spaceship, 4, 0));
+    assert_eq!(p.dx.abs(), 2);
+}
+
+#[test]
+fn names_spaceship_speeds() {
+    for (name, speed) in [
+        ("lwss.rle", "c/2 orthogonal"),
+        ("hwss.rle", "c/2 orthogonal"),
+        ("25p3h1v0.1.rle", "c/3 orthogonal"),
+        ("weekender.rle", "2c/7 orthogonal"),
+        ("copperhead.rle", "c/10 orthogonal"),
+        ("sirrobin.rle", "(2,1)c/6 oblique"),
+    ] {
+        let p = load_file(name).detect_period(100);
+        assert_eq!(p.kind, PeriodKind::Spaceship, "{}", name);
+        assert_eq!(p.speed(), speed, "{}", name);
+    }
+}
+
+#[test]
+fn still_patterns_have_no_speed() {
+    assert_eq!(load("x = 3, y = 1\n3o!").detect_period(10).speed(), "");
+}
@@ -0,0 +1,149 @@
+//! RLE parser tests, including a corpus run over every `.rle` file in `www/dist/patterns`.
+
+use std::fs;
+use std::path::PathBuf;
+use wasm_game_of_life::{Pattern, RleLimits, Universe};
+
+fn corpus() -> Vec<PathBuf> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/www/dist/patterns");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rle"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn read(path: &PathBuf) -> String {
+    String::from_utf8_lossy(&fs::read(path).unwrap()).into_owned()
+}
+
+#[test]
+fn corpus_parses_and_round_trips() {
+    let paths = corpus();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let pattern = Pattern::from_rle(&read(&path))
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let written = pattern.to_rle();
+        let reparsed = Pattern::from_rle(&written)
+            .unwrap_or_else(|e| panic!("{} (rewritten): {}", path.display(), e));
+        assert_eq!(pattern.cells(), reparsed.cells(), "{}", path.display());
+        assert_eq!(pattern.comments(), reparsed.comments(), "{}", path.display());
+        assert_eq!(
+            (pattern.width(), pattern.height()),
+            (reparsed.width(), reparsed.height()),
+            "{}",
+            path.display()
+        );
+        assert!(written.lines().all(|l| l.len() <= 70 || l.starts_with('#') || l.starts_with("x =")));
+    }
+}
+
+#[test]
+fn universe_export_matches_import() {
+    for name in ["gosperglidergun.rle", "b52bomber.rle", "turingmachine.rle"] {
+        let path = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/www/dist/patterns")).join(name);
+        let pattern = Pattern::from_rle(&read(&path)).unwrap();
+        let mut uni = Universe::new(0.0, 0.0);
+        uni.from_rle(read(&path)).unwrap();
+        assert_eq!(uni.population(), pattern.population());
+
+        let mut copy = Universe::new(0.0, 0.0);
+        copy.from_rle(uni.to_rle()).unwrap();
+        assert_eq!(copy.to_rle(), uni.to_rle());
+    }
+}
+
+#[test]
+fn reads_multi_state_letters() {
+    let pattern = Pattern::from_rle("x = 4, y = 2, rule = B2/S/C3\n.A2B$pA!").unwrap();
+    assert_eq!(pattern.cells(), &[(1, 0, 1), (2, 0, 2), (3, 0, 2), (0, 1, 25)]);
+}
+
+#[test]
+fn ignores_text_after_terminator() {
+    let pattern = Pattern::from_rle("x = 2, y = 1\n2o!\n3o$this is not data").unwrap();
+    assert_eq!(pattern.population(), 2);
+}
+
+#[test]
+fn reports_missing_header() {
+    let err = Pattern::from_rle("#N nothing\n").unwrap_err();
+    assert!(err.message.contains("header"), "{}", err);
+
+    let err = Pattern::from_rle("3o!").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 1));
+}
+
+#[test]
+fn reports_missing_height() {
+    let err = Pattern::from_rle("x = 3\n3o!").unwrap_err();
+    assert_eq!(err.line, 1);
+    assert!(err.message.contains("y ="), "{}", err);
+}
+
+#[test]
+fn reports_position_of_bad_character() {
+    let err = Pattern::from_rle("#C comment\nx = 3, y = 2\nobo$\no?o!").unwrap_err();
+    assert_eq!((err.line, err.column), (4, 2));
+}
+
+#[test]
+fn rejects_cells_outside_header() {
+    let err = Pattern::from_rle("x = 2, y = 1\n3o!").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 2));
+}
+
+#[test]
+fn enforces_limits() {
+    let err = Pattern::from_rle("x = 3, y = 1\n999999999o!").unwrap_err();
+    assert!(err.message.contains("limit"), "{}", err);
+
+    let limits = RleLimits {
+        max_dimension: 100,
+        max_cells: 4,
+    };
+    assert!(Pattern::parse_rle("x = 101, y = 1\no!", &limits).is_err());
+    assert!(Pattern::parse_rle("x = 5, y = 1\n5o!", &limits).is_err());
+    assert!(Pattern::parse_rle("x = 5, y = 1\n4o!", &limits).is_ok());
+
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set_rle_limits(100, 4);
+    assert!(uni.from_rle("x = 5, y = 1\n5o!".to_string()).is_err());
+    assert_eq!(uni.population(), 0);
+}
+
+#[test]
+fn rejects_unsupported_rule() {
+    let err = Pattern::from_rle("x = 1, y = 1, rule = B0/S8\no!").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 15));
+}
+
+#[test]
+fn exports_rectangles() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.from_rle("x = 3, y = 3\nbo$2bo$3o!".to_string()).unwrap();
+    uni.set(10.0, 10.0);
+    let rle = uni.to_rle_rect(0.0, 0.0, 2.0, 2.0);
+    assert!(rle.ends_with("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n"), "{}", rle);
+
+    // Corners may come in either order, and cells outside the rectangle are clipped.
+    let rle = uni.to_rle_rect(3.0, 2.0, 1.0, 1.0);
+    assert!(rle.ends_with("x = 3, y = 2, rule = B3/S23\nbo$2o!\n"), "{}", rle);
+    let clipped = Pattern::from_rle(&rle).unwrap();
+    assert_eq!(clipped.cells(), &[(1, 0, 1), (0, 1, 1), (1, 1, 1)]);
+
+    let rle = uni.to_rle_rect(20.0, 20.0, 25.0, 22.0);
+    assert!(rle.ends_with("x = 6, y = 3, rule = B3/S23\n!\n"), "{}", rle);
+    assert_eq!(Pattern::from_rle(&rle).unwrap().population(), 0);
+}
+
+#[test]
+fn writes_plaintext_comments_as_c_lines() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.from_cells("!Name: Glider\n.O.\n..O\nOOO\n", 0.0, 0.0).unwrap();
+    assert!(uni.to_rle().starts_with("#C Name: Glider\n"), "{}", uni.to_rle());
+    assert!(uni.to_macrocell().contains("\n#C Name: Glider\n"), "{}", uni.to_macrocell());
+}
@@ -0,0 +1,90 @@
+//! Rulestring parsing, through `Universe::set_rule` and the canonical form `Universe::rule`
+//! gives back.
+
+use wasm_game_of_life::Universe;
+
+fn canonical(rule: &str) -> Result<String, String> {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set_rule(rule)?;
+    Ok(uni.rule())
+}
+
+#[test]
+fn defaults_to_life() {
+    assert_eq!(Universe::new(0.0, 0.0).rule(), "B3/S23");
+}
+
+#[test]
+fn parses_life_like_rules() {
+    assert_eq!(canonical("B36/S23").unwrap(), "B36/S23");
+    assert_eq!(canonical("b36/s23").unwrap(), "B36/S23");
+    assert_eq!(canonical(" B3 / S23 ").unwrap(), "B3/S23");
+    assert_eq!(canonical("S23/B3").unwrap(), "B3/S23");
+    assert_eq!(canonical("B1357/S1357").unwrap(), "B1357/S1357");
+    assert_eq!(canonical("B3/S").unwrap(), "B3/S");
+}
+
+#[test]
+fn parses_legacy_survival_birth_order() {
+    assert_eq!(canonical("23/3").unwrap(), "B3/S23");
+    assert_eq!(canonical("23/36").unwrap(), "B36/S23");
+}
+
+#[test]
+fn parses_generations_rules() {
+    assert_eq!(canonical("B2/S/C3").unwrap(), "B2/S/C3");
+    assert_eq!(canonical("/2/3").unwrap(), "B2/S/C3");
+    assert_eq!(canonical("345/2/4").unwrap(), "B2/S345/C4");
+    assert_eq!(canonical("B2/S345/G4").unwrap(), "B2/S345/C4");
+    assert_eq!(canonical("B3/S23/C2").unwrap(), "B3/S23");
+}
+
+#[test]
+fn rejects_b0() {
+    let err = canonical("B03/S23").unwrap_err();
+    assert!(err.contains("B0"), "{}", err);
+    assert!(canonical("23/03").is_err());
+}
+
+#[test]
+fn rejects_mixed_notation() {
+    for rule in ["B3/23", "23/S3", "S23/3"] {
+        let err = canonical(rule).unwrap_err();
+        assert!(err.contains("mixes"), "{}: {}", rule, err);
+    }
+}
+
+#[test]
+fn rejects_malformed_rules() {
+    for rule in ["", "B3", "B3/S23/C3/D", "B39/S23", "B3/S2x", "B2/S/C1", "B2/S/C257", "B2/S/Cx"] {
+        assert!(canonical(rule).is_err(), "{}", rule);
+    }
+}
+
+#[test]
+fn loading_without_a_rule_means_life() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.from_rle("x = 3, y = 1, rule = B2/S/C3\n3o!".to_string()).unwrap();
+    assert_eq!(uni.rule(), "B2/S/C3");
+    uni.from_rle("x = 2, y = 2\n2o$2o!".to_string()).unwrap();
+    assert_eq!(uni.rule(), "B3/S23");
+    // Under Life the block is still; under Brian's Brain it would have died.
+    uni.advance_by(4);
+    assert_eq!(uni.population(), 4);
+
+    uni.set_rule("B36/S23").unwrap();
+    uni.from_cells("OO\nOO\n", 0.0, 0.0).unwrap();
+    assert_eq!(uni.rule(), "B3/S23");
+
+    uni.set_rule("B36/S23").unwrap();
+    uni.from_macrocell("[M2] (golly 4.0)\n$$$$$$**$**$\n").unwrap();
+    assert_eq!(uni.rule(), "B3/S23");
+}
+
+#[test]
+fn keeps_rule_on_error() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set_rule("B36/S23").unwrap();
+    assert!(uni.set_rule("B0/S8").is_err());
+    assert_eq!(uni.rule(), "B36/S23");
+}
@@ -0,0 +1,89 @@
+//! Pattern search with orientations, masks and regions.
+
+use wasm_game_of_life::{Pattern, Search, Universe};
+
+const GLIDER: [(i32, i32); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+fn mask(rle: &str) -> Pattern {
+    Pattern::from_rle(rle).unwrap()
+}
+
+fn positions(uni: &Universe, search: &Search) -> Vec<(i64, i64, u8)> {
+    uni.find_matches(search).iter().map(|m| (m.x, m.y, m.orientation)).collect()
+}
+
+/// Gliders as given at (10, 10), mirrored left to right at (30, 5) and transposed at (-40, -7),
+/// a lone block at (0, 0), and a block at (50, 50) with a cell just to its right.
+fn garden() -> Universe {
+    let mut uni = Universe::new(0.0, 0.0);
+    for &(x, y) in &GLIDER {
+        uni.set((10 + x) as f64, (10 + y) as f64);
+        uni.set((30 + 2 - x) as f64, (5 + y) as f64);
+        uni.set((-40 + y) as f64, (-7 + x) as f64);
+    }
+    for &(x, y) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+        uni.set(x as f64, y as f64);
+        uni.set((50 + x) as f64, (50 + y) as f64);
+    }
+    uni.set(52.0, 50.0);
+    uni
+}
+
+#[test]
+fn finds_target_as_given() {
+    let search = Search::from_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+    assert_eq!(positions(&garden(), &search), vec![(10, 10, 0)]);
+}
+
+#[test]
+fn finds_all_orientations() {
+    let mut search = Search::from_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+    search.set_all_orientations(true);
+    assert_eq!(positions(&garden(), &search), vec![(-40, -7, 4), (30, 5, 1), (10, 10, 0)]);
+}
+
+#[test]
+fn frame_cells_must_be_dead() {
+    let search = Search::from_rle("x = 2, y = 2\n2o$2o!").unwrap();
+    assert_eq!(positions(&garden(), &search), vec![(0, 0, 0), (50, 50, 0)]);
+
+    let isolated = Search::from_rle("x = 4, y = 4\n4b$b2ob$b2ob$4b!").unwrap();
+    assert_eq!(positions(&garden(), &isolated), vec![(-1, -1, 0)]);
+}
+
+#[test]
+fn dont_care_cells_are_ignored() {
+    let mut search = Search::from_rle("x = 4, y = 4\n4b$b2ob$b2ob$4b!").unwrap();
+    search.set_dont_care(&mask("x = 4, y = 4\n4o$o2bo$o2bo$4o!"));
+    assert_eq!(positions(&garden(), &search), vec![(-1, -1, 0), (49, 49, 0)]);
+}
+
+#[test]
+fn must_be_dead_cells_can_extend_past_the_frame() {
+    let mut search = Search::from_rle("x = 2, y = 2\n2o$2o!").unwrap();
+    search.set_must_be_dead(&mask("x = 3, y = 3\n2bo$2bo$3o!"));
+    assert_eq!(positions(&garden(), &search), vec![(0, 0, 0)]);
+}
+
+#[test]
+fn region_limits_matches() {
+    let mut search = Search::from_rle("x = 2, y = 2\n2o$2o!").unwrap();
+    search.set_region(60.0, 60.0, 40.0, 40.0);
+    assert_eq!(positions(&garden(), &search), vec![(50, 50, 0)]);
+    search.set_region(50.0, 50.0, 51.0, 51.0);
+    assert_eq!(positions(&garden(), &search), vec![(50, 50, 0)]);
+    search.set_region(50.0, 50.0, 51.0, 50.0);
+    assert!(positions(&garden(), &search).is_empty());
+}
+
+#[test]
+fn finds_matches_far_apart() {
+    let mut uni = Universe::new(0.0, 0.0);
+    for &(x, y) in &GLIDER {
+        uni.set(x as f64, y as f64);
+        uni.set(1e9 + x as f64, -1e9 + y as f64);
+    }
+    let search = Search::from_rle("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+    assert_eq!(positions(&uni, &search), vec![(1_000_000_000, -1_000_000_000, 0), (0, 0, 0)]);
+    assert!(positions(&Universe::new(0.0, 0.0), &search).is_empty());
+}
@@ -0,0 +1,107 @@
+//! Copying, cutting, clearing and filling rectangular selections.
+
+mod common;
+
+use common::{cells, scatter, Cells, Lcg};
+use wasm_game_of_life::{PasteMode, Universe};
+
+#[test]
+fn selections_match_cell_by_cell_reference() {
+    let mut rng = Lcg(21);
+    for round in 0..40 {
+        let mut uni = scatter(&mut rng, -20, -20, 40, 40, 400, 3);
+        let before = cells(&uni);
+        let (x0, y0) = (rng.within(-25, 50), rng.within(-25, 50));
+        let (x1, y1) = (x0 + rng.within(0, 20), y0 + rng.within(0, 20));
+        let inside = |&(x, y): &(i64, i64)| x >= x0 && x <= x1 && y >= y0 && y <= y1;
+        let within: Cells = before.iter().filter(|(pos, _)| inside(pos)).map(|(&pos, &s)| (pos, s)).collect();
+        let outside: Cells = before.iter().filter(|(pos, _)| !inside(pos)).map(|(&pos, &s)| (pos, s)).collect();
+
+        // Corners may come in either order.
+        let pattern = uni.copy_rect(x1 as f64, y1 as f64, x0 as f64, y0 as f64);
+        assert_eq!((pattern.width() as i64, pattern.height() as i64), (x1 - x0 + 1, y1 - y0 + 1));
+        let copied: Cells = pattern.cells().iter().map(|&(x, y, s)| ((x as i64 + x0, y as i64 + y0), s)).collect();
+        assert_eq!(copied, within);
+
+        let (left, top, right, bottom) = (x0 as f64, y0 as f64, x1 as f64, y1 as f64);
+        let expected = match round % 5 {
+            0 => {
+                let cut = uni.cut_rect(left, top, right, bottom);
+                assert_eq!(cut.cells(), pattern.cells());
+                outside
+            }
+            1 => {
+                uni.clear_rect(right, top, left, bottom);
+                outside
+            }
+            2 => {
+                uni.clear_outside(left, top, right, bottom);
+                within
+            }
+            3 => {
+                uni.fill_rect(left, top, right, bottom, 2).unwrap();
+                let mut expected = outside;
+                for y in y0..=y1 {
+                    for x in x0..=x1 {
+                        expected.insert((x, y), 2);
+                    }
+                }
+                expected
+            }
+            _ => {
+                uni.fill_rect(left, top, right, bottom, 0).unwrap();
+                outside
+            }
+        };
+        assert_eq!(cells(&uni), expected, "round {}", round);
+        if expected != before {
+            assert!(uni.undo());
+            assert_eq!(cells(&uni), before);
+        }
+    }
+}
+
+#[test]
+fn copy_carries_the_rule() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set_rule("B36/S23").unwrap();
+    uni.set(1.0, 1.0);
+    let pattern = uni.copy_rect(0.0, 0.0, 2.0, 2.0);
+    assert_eq!(pattern.rule().as_deref(), Some("B36/S23"));
+    assert_eq!(pattern.cells(), &[(1, 1, 1)]);
+    assert_eq!(uni.population(), 1);
+}
+
+#[test]
+fn fills_huge_rectangles() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.fill_rect(-100_000.0, -100_000.0, 99_999.0, 99_999.0, 1).unwrap();
+    assert_eq!(uni.population(), 40_000_000_000);
+    // Uniform quadrants share nodes, so a huge fill stays small.
+    assert!(uni.node_count() < 1000);
+    uni.clear_rect(-99_999.0, -99_999.0, 99_998.0, 99_998.0);
+    assert_eq!(uni.population(), 4 * 199_999);
+}
+
+#[test]
+fn rejects_unknown_states() {
+    let mut uni = Universe::new(0.0, 0.0);
+    assert!(uni.fill_rect(0.0, 0.0, 3.0, 3.0, 256).is_err());
+    assert_eq!(uni.population(), 0);
+    assert!(!uni.undo());
+}
+
+#[test]
+fn fills_under_a_far_flung_root() {
+    // A glider far from a block stretches the root well past what a root-sized filled tree
+    // could count.
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.paste_rle("x = 3, y = 3\nbo$2bo$3o!", 0.0, 0.0, PasteMode::Or).unwrap();
+    uni.paste_rle("x = 2, y = 2\n2o$2o!", -10.0, -10.0, PasteMode::Or).unwrap();
+    uni.advance_by(1 << 36);
+    let before = uni.population();
+    uni.fill_rect(0.0, 0.0, 1.0, 1.0, 1).unwrap();
+    assert_eq!(uni.population(), before + 4);
+    assert_eq!(uni.get(1.0, 1.0), 1);
+    assert_eq!(uni.get(2.0, 2.0), 0);
+}
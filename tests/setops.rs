@@ -0,0 +1,120 @@
+//! Union, intersection, difference and symmetric difference of two universes.
+
+mod common;
+
+use common::{cells, load_file, scatter, Cells, Lcg};
+use wasm_game_of_life::Universe;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Union,
+    Intersect,
+    Subtract,
+    Xor,
+}
+
+const OPS: [Op; 4] = [Op::Union, Op::Intersect, Op::Subtract, Op::Xor];
+
+fn apply(uni: &mut Universe, other: &Universe, op: Op) {
+    match op {
+        Op::Union => uni.union_with(other),
+        Op::Intersect => uni.intersect_with(other),
+        Op::Subtract => uni.subtract(other),
+        Op::Xor => uni.xor_with(other),
+    }
+}
+
+fn copy(uni: &Universe) -> Universe {
+    let mut copy = Universe::new(0.0, 0.0);
+    copy.union_with(uni);
+    copy
+}
+
+/// The result of `op` cell by cell. Where both are live, a union takes the state from `b` and
+/// an intersection keeps the state from `a`.
+fn expected(a: &Cells, b: &Cells, op: Op) -> Cells {
+    let mut cells = Cells::new();
+    for &pos in a.keys().chain(b.keys()) {
+        let (sa, sb) = (a.get(&pos).copied().unwrap_or(0), b.get(&pos).copied().unwrap_or(0));
+        let state = match op {
+            Op::Union if sb != 0 => sb,
+            Op::Union => sa,
+            Op::Intersect if sb != 0 => sa,
+            Op::Subtract if sb == 0 => sa,
+            Op::Xor if sa == 0 => sb,
+            Op::Xor if sb == 0 => sa,
+            _ => 0,
+        };
+        if state != 0 {
+            cells.insert(pos, state);
+        }
+    }
+    cells
+}
+
+#[test]
+fn matches_cell_by_cell_reference() {
+    let mut rng = Lcg(5);
+    for round in 0..20 {
+        let a = scatter(&mut rng, -20 + round % 7, -13, 25 + round as u64, 30, 200, 2);
+        let b = scatter(&mut rng, -5, -30 + round, 30, 22, 150, 2);
+        let (ca, cb) = (cells(&a), cells(&b));
+        for &op in &OPS {
+            let mut uni = copy(&a);
+            apply(&mut uni, &b, op);
+            assert_eq!(cells(&uni), expected(&ca, &cb, op), "round {} {:?}", round, op);
+            // The other universe is left alone.
+            assert_eq!(cells(&b), cb);
+        }
+    }
+}
+
+#[test]
+fn empty_operands() {
+    let mut rng = Lcg(9);
+    let a = scatter(&mut rng, 0, 0, 20, 20, 80, 2);
+    let empty = Universe::new(0.0, 0.0);
+    for &op in &OPS {
+        let mut uni = copy(&a);
+        apply(&mut uni, &empty, op);
+        assert_eq!(cells(&uni), expected(&cells(&a), &Cells::new(), op), "{:?}", op);
+
+        let mut uni = Universe::new(0.0, 0.0);
+        apply(&mut uni, &a, op);
+        assert_eq!(cells(&uni), expected(&Cells::new(), &cells(&a), op), "{:?}", op);
+    }
+}
+
+#[test]
+fn operands_far_apart() {
+    let mut a = Universe::new(0.0, 0.0);
+    a.set(0.0, 0.0);
+    let mut b = Universe::new(0.0, 0.0);
+    b.set(1e12, -1e12);
+    a.union_with(&b);
+    assert_eq!(a.population(), 2);
+    assert_eq!(a.get(1e12, -1e12), 1);
+    a.xor_with(&b);
+    assert_eq!(a.population(), 1);
+    assert_eq!(a.get(0.0, 0.0), 1);
+}
+
+#[test]
+fn comparing_runs_and_undo() {
+    let mut a = load_file("rpentomino.rle");
+    let mut b = load_file("rpentomino.rle");
+    a.advance_by(100);
+    b.advance_by(100);
+    let before = cells(&a);
+    a.xor_with(&b);
+    assert_eq!(a.population(), 0);
+    assert!(a.undo());
+    assert_eq!(cells(&a), before);
+
+    // Subtracting something disjoint changes nothing, so it is not recorded.
+    let mut far = Universe::new(0.0, 0.0);
+    far.set(1000.0, 1000.0);
+    a.subtract(&far);
+    assert!(a.undo());
+    assert_eq!(a.generation(), "0");
+}
@@ -0,0 +1,93 @@
+//! Seeded random soups, with and without symmetry.
+
+mod common;
+
+use common::cells;
+use wasm_game_of_life::{Symmetry, Universe};
+
+/// The states of the `width` × `height` rectangle at (x0, y0), row by row.
+fn grid(uni: &Universe, x0: i64, y0: i64, width: usize, height: usize) -> Vec<Vec<usize>> {
+    (0..height)
+        .map(|y| (0..width).map(|x| uni.get((x0 + x as i64) as f64, (y0 + y as i64) as f64)).collect())
+        .collect()
+}
+
+#[test]
+fn same_seed_gives_same_soup() {
+    let mut a = Universe::new(0.0, 0.0);
+    a.random_fill(-8.0, -8.0, 7.0, 7.0, 0.5, 42).unwrap();
+    // Corners may come in either order, and cells outside the rectangle are kept.
+    let mut b = Universe::new(0.0, 0.0);
+    b.set(100.0, 100.0);
+    b.random_fill(7.0, 7.0, -8.0, -8.0, 0.5, 42).unwrap();
+    assert_eq!(grid(&a, -8, -8, 16, 16), grid(&b, -8, -8, 16, 16));
+    assert_eq!(b.get(100.0, 100.0), 1);
+
+    // The soup depends only on the seed and size, not on where it goes.
+    let mut c = Universe::new(0.0, 0.0);
+    c.random_fill(0.0, 0.0, 15.0, 15.0, 0.5, 42).unwrap();
+    assert_eq!(grid(&a, -8, -8, 16, 16), grid(&c, 0, 0, 16, 16));
+    let mut d = Universe::new(0.0, 0.0);
+    d.random_fill(0.0, 0.0, 15.0, 15.0, 0.5, 43).unwrap();
+    assert_ne!(grid(&c, 0, 0, 16, 16), grid(&d, 0, 0, 16, 16));
+}
+
+#[test]
+fn density_sets_the_fraction_alive() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.random_fill(0.0, 0.0, 499.0, 499.0, 0.3, 1).unwrap();
+    let fraction = uni.population() as f64 / 250_000.0;
+    assert!((fraction - 0.3).abs() < 0.01, "{}", fraction);
+
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.random_fill(0.0, 0.0, 9.0, 9.0, 1.0, 1).unwrap();
+    assert_eq!(uni.population(), 100);
+    // An empty soup clears the rectangle.
+    uni.random_fill(0.0, 0.0, 9.0, 9.0, 0.0, 1).unwrap();
+    assert_eq!(uni.population(), 0);
+    assert!(uni.undo());
+    assert_eq!(uni.population(), 100);
+}
+
+#[test]
+fn symmetric_soups() {
+    for &(symmetry, width, height) in &[
+        (Symmetry::C2, 13, 8),
+        (Symmetry::C4, 12, 12),
+        (Symmetry::D2, 9, 14),
+        (Symmetry::D4, 10, 7),
+        (Symmetry::D8, 11, 11),
+    ] {
+        let mut uni = Universe::new(0.0, 0.0);
+        let (x1, y1) = ((3 + width - 1) as f64, (4 + height - 1) as f64);
+        uni.random_fill_symmetric(3.0, 4.0, x1, y1, 0.4, 7, symmetry).unwrap();
+        assert!(uni.population() > 0);
+        let g = grid(&uni, 3, 4, width, height);
+        let (w, h) = (width - 1, height - 1);
+        for y in 0..height {
+            for x in 0..width {
+                let state = g[y][x];
+                match symmetry {
+                    Symmetry::C2 => assert_eq!(state, g[h - y][w - x]),
+                    Symmetry::C4 => assert_eq!(state, g[x][w - y]),
+                    Symmetry::D2 => assert_eq!(state, g[y][w - x]),
+                    Symmetry::D4 => assert_eq!((state, state), (g[y][w - x], g[h - y][x])),
+                    _ => assert_eq!((state, state), (g[x][y], g[y][w - x])),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set(0.0, 0.0);
+    let before = cells(&uni);
+    assert!(uni.random_fill_symmetric(0.0, 0.0, 9.0, 4.0, 0.5, 1, Symmetry::C4).is_err());
+    assert!(uni.random_fill_symmetric(0.0, 0.0, 9.0, 4.0, 0.5, 1, Symmetry::D8).is_err());
+    assert!(uni.random_fill(0.0, 0.0, 9.0, 4.0, 1.5, 1).is_err());
+    assert!(uni.random_fill(0.0, 0.0, 9.0, 4.0, -0.1, 1).is_err());
+    assert!(uni.random_fill(-1e9, -1e9, 1e9, 1e9, 0.5, 1).is_err());
+    assert_eq!(cells(&uni), before);
+}
@@ -0,0 +1,167 @@
+//! Rotations, reflections and translations of the whole universe and of rectangles.
+
+mod common;
+
+use common::{cells, load_file, scatter, Cells, Lcg};
+use wasm_game_of_life::{Transform, Universe};
+
+const ALL: [Transform; 7] = [
+    Transform::Rotate90,
+    Transform::Rotate180,
+    Transform::Rotate270,
+    Transform::FlipHorizontal,
+    Transform::FlipVertical,
+    Transform::FlipDiagonal,
+    Transform::FlipAntiDiagonal,
+];
+
+/// Where `transform` sends (x, y) about the origin, on screen (`y` down).
+fn map(transform: Transform, x: i64, y: i64) -> (i64, i64) {
+    match transform {
+        Transform::Rotate90 => (-y, x),
+        Transform::Rotate180 => (-x, -y),
+        Transform::Rotate270 => (y, -x),
+        Transform::FlipHorizontal => (-x, y),
+        Transform::FlipVertical => (x, -y),
+        Transform::FlipDiagonal => (y, x),
+        Transform::FlipAntiDiagonal => (-y, -x),
+    }
+}
+
+/// `cells` transformed, then moved so that their bounding box starts at (x, y).
+fn transformed(cells: &Cells, transform: Transform, x: i64, y: i64) -> Cells {
+    let mapped: Cells = cells.iter().map(|(&(cx, cy), &s)| (map(transform, cx, cy), s)).collect();
+    let min_x = mapped.keys().map(|&(cx, _)| cx).min().unwrap();
+    let min_y = mapped.keys().map(|&(_, cy)| cy).min().unwrap();
+    mapped.into_iter().map(|((cx, cy), s)| ((cx - min_x + x, cy - min_y + y), s)).collect()
+}
+
+fn inside(pos: (i64, i64), x0: i64, y0: i64, x1: i64, y1: i64) -> bool {
+    pos.0 >= x0 && pos.0 <= x1 && pos.1 >= y0 && pos.1 <= y1
+}
+
+#[test]
+fn transforms_whole_universe_about_its_centre() {
+    let mut rng = Lcg(7);
+    for &transform in &ALL {
+        let mut uni = scatter(&mut rng, -20, 5, 17, 9, 60, 2);
+        let before = cells(&uni);
+        let bbox = uni.bounding_box().unwrap();
+        uni.transform(transform);
+        let after = uni.bounding_box().unwrap();
+        assert_eq!(cells(&uni), transformed(&before, transform, after.min_x, after.min_y), "{:?}", transform);
+        assert!((bbox.min_x + bbox.max_x - after.min_x - after.max_x).abs() <= 1);
+        assert!((bbox.min_y + bbox.max_y - after.min_y - after.max_y).abs() <= 1);
+
+        assert!(uni.undo());
+        assert_eq!(cells(&uni), before);
+    }
+}
+
+/// The cells of `uni` moved so that their bounding box starts at the origin.
+fn shape(uni: &Universe) -> Cells {
+    let cells = cells(uni);
+    let min_x = cells.keys().map(|&(x, _)| x).min().unwrap();
+    let min_y = cells.keys().map(|&(_, y)| y).min().unwrap();
+    cells.into_iter().map(|((x, y), s)| ((x - min_x, y - min_y), s)).collect()
+}
+
+#[test]
+fn quarter_turns_compose() {
+    let mut uni = load_file("gosperglidergun.rle");
+    let before = shape(&uni);
+    for _ in 0..4 {
+        uni.transform(Transform::Rotate90);
+    }
+    assert_eq!(shape(&uni), before);
+    uni.transform(Transform::FlipDiagonal);
+    uni.transform(Transform::FlipAntiDiagonal);
+    let mut turned = load_file("gosperglidergun.rle");
+    turned.transform(Transform::Rotate180);
+    assert_eq!(shape(&uni), shape(&turned));
+}
+
+#[test]
+fn transforms_rectangles_in_place() {
+    let mut rng = Lcg(11);
+    for &transform in &ALL {
+        for _ in 0..4 {
+            let mut uni = scatter(&mut rng, -30, -30, 60, 60, 900, 2);
+            let before = cells(&uni);
+            let (x0, y0) = (rng.within(-10, 10), rng.within(-12, 10));
+            let (w, h) = (rng.within(1, 14), rng.within(1, 14));
+            let (x1, y1) = (x0 + w - 1, y0 + h - 1);
+            uni.transform_rect(x0 as f64, y0 as f64, x1 as f64, y1 as f64, transform);
+
+            // The result is centred on the original rectangle, rounding towards the top left.
+            let (nw, nh) = if map(transform, 1, 0).0 == 0 { (h, w) } else { (w, h) };
+            let (nx, ny) = (x0 + (w - nw).div_euclid(2), y0 + (h - nh).div_euclid(2));
+            let mut expected: Cells = before
+                .iter()
+                .filter(|(&pos, _)| !inside(pos, x0, y0, x1, y1) && !inside(pos, nx, ny, nx + nw - 1, ny + nh - 1))
+                .map(|(&pos, &s)| (pos, s))
+                .collect();
+            // Transform the rectangle's corners along with its cells, so that an empty edge
+            // keeps its place.
+            let mut selection: Cells = before
+                .iter()
+                .filter(|(&pos, _)| inside(pos, x0, y0, x1, y1))
+                .map(|(&pos, &s)| (pos, s))
+                .collect();
+            selection.entry((x0, y0)).or_insert(0);
+            selection.entry((x1, y1)).or_insert(0);
+            expected.extend(transformed(&selection, transform, nx, ny).into_iter().filter(|&(_, s)| s != 0));
+
+            assert_eq!(cells(&uni), expected, "{:?} on {},{} {}x{}", transform, x0, y0, w, h);
+            if expected != before {
+                assert!(uni.undo());
+                assert_eq!(cells(&uni), before);
+            }
+        }
+    }
+}
+
+#[test]
+fn translates_universe_and_rectangles() {
+    let mut rng = Lcg(3);
+    let mut uni = scatter(&mut rng, 0, 0, 10, 10, 30, 2);
+    let before = cells(&uni);
+    uni.translate(-7.0, 12.0);
+    let moved: Cells = before.iter().map(|(&(x, y), &s)| ((x - 7, y + 12), s)).collect();
+    assert_eq!(cells(&uni), moved);
+
+    let mut uni = scatter(&mut rng, -20, -20, 40, 40, 500, 2);
+    let before = cells(&uni);
+    uni.translate_rect(5.0, 6.0, -4.0, -3.0, 9.0, -2.0);
+    let source = |pos| inside(pos, -4, -3, 5, 6);
+    let target = |pos| inside(pos, 5, -5, 14, 4);
+    let mut expected: Cells = before
+        .iter()
+        .filter(|(&pos, _)| !source(pos) && !target(pos))
+        .map(|(&pos, &s)| (pos, s))
+        .collect();
+    expected.extend(before.iter().filter(|(&pos, _)| source(pos)).map(|(&(x, y), &s)| ((x + 9, y - 2), s)));
+    assert_eq!(cells(&uni), expected);
+}
+
+#[test]
+fn large_transforms_reuse_nodes() {
+    let mut uni = load_file("turingmachine.rle");
+    let population = uni.population();
+    let nodes = uni.node_count();
+    uni.transform(Transform::Rotate90);
+    assert_eq!(uni.population(), population);
+    // Rotating builds one new node per distinct node, not one per cell.
+    assert!(uni.node_count() < 2 * nodes);
+    uni.transform(Transform::Rotate270);
+    assert_eq!(uni.population(), population);
+}
+
+#[test]
+fn empty_universe_is_unchanged() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.transform(Transform::Rotate90);
+    uni.transform_rect(0.0, 0.0, 9.0, 4.0, Transform::FlipDiagonal);
+    assert_eq!(uni.population(), 0);
+    assert!(!uni.undo());
+}
@@ -0,0 +1,120 @@
+//! Undo, redo and reset over root snapshots.
+
+mod common;
+
+use common::{cells, load, Cells};
+use wasm_game_of_life::Universe;
+
+const ACORN: &str = "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!";
+
+fn state(uni: &Universe) -> (Cells, String) {
+    (cells(uni), uni.generation())
+}
+
+#[test]
+fn undoes_and_redoes_edits_and_steps() {
+    let mut uni = load(ACORN);
+    let loaded = state(&uni);
+    assert!(!uni.undo());
+
+    uni.set(100.0, 100.0);
+    let edited = state(&uni);
+    uni.advance_by(50);
+    let stepped = state(&uni);
+    uni.toggle(0.0, 0.0);
+    uni.advance_by(77);
+    let last = state(&uni);
+
+    assert!(uni.undo());
+    assert!(uni.undo());
+    assert_eq!(state(&uni), stepped);
+    assert!(uni.undo());
+    assert_eq!(state(&uni), edited);
+    assert!(uni.undo());
+    assert_eq!(state(&uni), loaded);
+    assert!(!uni.undo());
+
+    for _ in 0..4 {
+        assert!(uni.redo());
+    }
+    assert!(!uni.redo());
+    assert_eq!(state(&uni), last);
+}
+
+#[test]
+fn skips_edits_that_change_nothing() {
+    let mut uni = load(ACORN);
+    uni.set(100.0, 100.0);
+    uni.set(100.0, 100.0);
+    uni.clear_cell(-50.0, -50.0);
+    assert!(uni.undo());
+    assert!(!uni.undo());
+}
+
+#[test]
+fn new_edit_discards_redo() {
+    let mut uni = load(ACORN);
+    uni.advance_by(10);
+    assert!(uni.undo());
+    uni.toggle(20.0, 20.0);
+    assert!(!uni.redo());
+    assert_eq!(uni.generation(), "0");
+}
+
+#[test]
+fn reset_returns_to_last_load_and_is_undoable() {
+    let mut uni = load(ACORN);
+    let loaded = state(&uni);
+    uni.advance_by(100);
+    uni.set(3.0, 3.0);
+    let edited = state(&uni);
+
+    uni.reset_to_start();
+    assert_eq!(state(&uni), loaded);
+    assert!(uni.undo());
+    assert_eq!(state(&uni), edited);
+
+    // A second load replaces the acorn rather than adding to it.
+    uni.from_rle("x = 3, y = 1\n3o!".to_string()).unwrap();
+    let reloaded = state(&uni);
+    assert_eq!(reloaded, (cells(&load("x = 3, y = 1\n3o!")), "0".to_string()));
+    assert!(!uni.undo());
+    uni.advance_by(5);
+    uni.reset_to_start();
+    assert_eq!(state(&uni), reloaded);
+}
+
+#[test]
+fn depth_bounds_history() {
+    let mut uni = load(ACORN);
+    for _ in 0..5 {
+        uni.advance_by(1);
+    }
+    uni.set_undo_depth(2);
+    assert!(uni.undo());
+    assert!(uni.undo());
+    assert!(!uni.undo());
+    assert_eq!(uni.generation(), "3");
+
+    for _ in 0..5 {
+        uni.advance_by(1);
+    }
+    assert!(uni.undo());
+    assert!(uni.undo());
+    assert!(!uni.undo());
+}
+
+#[test]
+fn history_survives_collections() {
+    let mut uni = load(ACORN);
+    uni.set_gc_threshold(300);
+    let loaded = state(&uni);
+    for _ in 0..20 {
+        uni.advance_by(10);
+    }
+    uni.collect_garbage();
+    uni.undo();
+    assert_eq!(uni.generation(), "190");
+    uni.reset_to_start();
+    assert_eq!(state(&uni), loaded);
+}
@@ -0,0 +1,92 @@
+//! Bounding boxes of the live pattern and views framing it.
+
+mod common;
+
+use common::{load_file, Cells, LIFE};
+use wasm_game_of_life::{BoundingBox, Universe};
+
+fn extent(cells: &Cells) -> BoundingBox {
+    BoundingBox {
+        min_x: cells.keys().map(|&(x, _)| x).min().unwrap(),
+        min_y: cells.keys().map(|&(_, y)| y).min().unwrap(),
+        max_x: cells.keys().map(|&(x, _)| x).max().unwrap(),
+        max_y: cells.keys().map(|&(_, y)| y).max().unwrap(),
+    }
+}
+
+#[test]
+fn empty_universe_has_no_box() {
+    let mut uni = Universe::new(0.0, 0.0);
+    assert_eq!(uni.bounding_box(), None);
+    assert!(uni.fit_view(800.0, 600.0).is_none());
+    uni.set(5.0, 5.0);
+    uni.clear_cell(5.0, 5.0);
+    assert_eq!(uni.bounding_box(), None);
+}
+
+#[test]
+fn box_tracks_live_cells() {
+    let mut expected: Cells = [((-37, 5), 1), ((-36, 5), 1), ((-35, 5), 1), ((250, -1000), 1), ((3, 4), 2)]
+        .iter()
+        .copied()
+        .collect();
+    let mut uni = Universe::new(0.0, 0.0);
+    for (&(x, y), &state) in &expected {
+        uni.set_state(x as f64, y as f64, state).unwrap();
+    }
+    assert_eq!(uni.bounding_box(), Some(extent(&expected)));
+
+    uni.clear_cell(250.0, -1000.0);
+    uni.clear_cell(3.0, 4.0);
+    expected.remove(&(250, -1000));
+    expected.remove(&(3, 4));
+    assert_eq!(uni.bounding_box(), Some(extent(&expected)));
+
+    uni.advance_by(1);
+    assert_eq!(uni.bounding_box(), Some(extent(&LIFE.step(&expected))));
+}
+
+#[test]
+fn box_follows_evolution() {
+    let mut uni = load_file("gosperglidergun.rle");
+    let mut expected = common::cells(&uni);
+    for _ in 0..4 {
+        uni.advance_by(60);
+        expected = LIFE.run(&expected, 60);
+        assert_eq!(uni.bounding_box(), Some(extent(&expected)));
+    }
+}
+
+#[test]
+fn view_frames_the_pattern() {
+    let mut uni = load_file("gosperglidergun.rle");
+    uni.advance_by(200);
+    let bbox = uni.bounding_box().unwrap();
+    for (width, height) in [(800.0, 600.0), (300.0, 900.0), (1000.0, 1000.0)] {
+        let view = uni.fit_view(width, height).unwrap();
+        assert!((view.width / view.height - width / height).abs() < 1e-9);
+        assert!(view.x <= bbox.min_x as f64 && view.y <= bbox.min_y as f64);
+        assert!(view.x + view.width >= (bbox.max_x + 1) as f64);
+        assert!(view.y + view.height >= (bbox.max_y + 1) as f64);
+        let centre_x = view.x + view.width / 2.0;
+        assert!((centre_x - (bbox.min_x + bbox.max_x + 1) as f64 / 2.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn view_keeps_a_minimum_size() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set(0.0, 0.0);
+    let view = uni.fit_view(400.0, 200.0).unwrap();
+    assert_eq!((view.width, view.height), (20.0, 10.0));
+    assert_eq!((view.x, view.y), (-9.5, -4.5));
+}
+
+#[test]
+fn no_view_fits_an_empty_screen() {
+    let mut uni = Universe::new(0.0, 0.0);
+    uni.set(0.0, 0.0);
+    for (width, height) in [(800.0, 0.0), (0.0, 600.0), (0.0, 0.0), (-800.0, 600.0), (f64::NAN, 600.0)] {
+        assert!(uni.fit_view(width, height).is_none(), "{} x {}", width, height);
+    }
+}
@@ -1,4 +1,4 @@
-import { Universe } from "wasm-game-of-life";
+import { Universe, Search, Transform, Pattern, PasteMode } from "wasm-game-of-life";
 
 const mouseXElem = document.getElementById('mouseX');
 const mouseYElem = document.getElementById('mouseY');
@@ -8,6 +8,7 @@ const windowYElem = document.getElementById('windowY');
 
 const fpsCounterElem = document.getElementById('fpsCounter');
 const ipsCounterElem = document.getElementById('ipsCounter');
+const generationCounterElem = document.getElementById('generationCounter');
 
 const fpsInput = document.getElementById('fpsInput');
 const ipsInput = document.getElementById('ipsInput');
@@ -25,6 +26,17 @@ var touchScaling = false;
 var lastTouchScaleDist = 0;
 var lastMousePos = null;
 var patternOnDeck = null;
+
+// The `Transform` equal to each placement matrix, keyed by its entries in row order.
+const orientations = {
+    '0,-1,1,0': Transform.Rotate90,
+    '-1,0,0,-1': Transform.Rotate180,
+    '0,1,-1,0': Transform.Rotate270,
+    '-1,0,0,1': Transform.FlipHorizontal,
+    '1,0,0,-1': Transform.FlipVertical,
+    '0,1,1,0': Transform.FlipDiagonal,
+    '0,-1,-1,0': Transform.FlipAntiDiagonal,
+};
 var previousIPS = null; 
 
 var viewX = 0;
@@ -231,8 +243,8 @@ function pack_coords(coords_list) {
 }
 function unpack_coords(coords_list) {
     let results = [];
-    for (let i = 0; i < coords_list.length; i += 2) {
-        results.push({x: coords_list[i], y: coords_list[i + 1]});
+    for (let i = 0; i < coords_list.length; i += 3) {
+        results.push({x: coords_list[i], y: coords_list[i + 1], state: coords_list[i + 2]});
     }
     // console.log(results.length)
     return results;
@@ -322,18 +334,23 @@ function resizeCanvas(canvas) {
                 [Math.round(Math.cos(t)), Math.round(-Math.sin(t))],
                 [Math.round(Math.sin(t)), Math.round(Math.cos(t))]
             ];
-            patternOnDeck.data.coords.forEach(coord => {
-                let pos = [
-                    [coord.x],
-                    [coord.y]
-                ];
-                pos = matMul(rotMat, pos);
-                pos = matMul(transform, pos);
-                uni.set(pos[0][0] + patternOnDeck.meta.xoffset, pos[1][0] + patternOnDeck.meta.yoffset);
-                // let x = (pos[0] * transform[0][0]) + (pos[1] * transform[0][1]) + patternOnDeck.meta.xoffset;
-                // let y = (pos[0] * transform[1][0]) + (pos[1] * transform[1][1]) + patternOnDeck.meta.yoffset;
-                // uni.set(x, y);
-            });
+            let m = matMul(transform, rotMat);
+            let pattern = Pattern.from_rle(patternOnDeck.text);
+            let orientation = orientations[m.flat().join(',')];
+            if (orientation !== undefined) {
+                let transformed = pattern.transformed(orientation);
+                pattern.free();
+                pattern = transformed;
+            }
+            // The matrix maps the pattern around its top-left cell, so find where its
+            // rectangle's top-left corner ends up.
+            let w = patternOnDeck.data.width - 1;
+            let h = patternOnDeck.data.height - 1;
+            let corners = [[0, 0], [w, 0], [0, h], [w, h]].map(c => matMul(m, [[c[0]], [c[1]]]));
+            let x = Math.min(...corners.map(c => c[0][0])) + patternOnDeck.meta.xoffset;
+            let y = Math.min(...corners.map(c => c[1][0])) + patternOnDeck.meta.yoffset;
+            uni.paste(pattern, x, y, PasteMode.Or);
+            pattern.free();
             patternOnDeck = null;
 
         }
@@ -552,41 +569,35 @@ function draw(coords, viewX, viewY, viewWidth, viewHeight, viewLevel) {
     }
 
 
-    function findPattern(target, coords) {
-        let cmap = {};
-        coords.forEach(c => {
-            cmap[[c.x, c.y]] = true;
-        });
-        
-        coords.forEach(c => {
-            let matches = true;
-            for (let i = 0; i < target.length; i++) {
-                if (cmap[[c.x + target[i].x, c.y + target[i].y]] !== true) {
-                    matches = false;
-                    break;
-                }
-            }
-            if (matches) {
-                ctx.strokeStyle = "red";
-                ctx.beginPath();
-                let x_adj = (c.x - viewX) * cellWidth;
-                let y_adj =  (c.y - viewY) * cellHeight;
-                ctx.rect(x_adj, y_adj, 3 * cellWidth, 3 * cellHeight);
-                ctx.stroke();
-            }
-        });
+    if (censusBoxes !== null) {
+        ctx.strokeStyle = "red";
+        for (let i = 0; i < censusBoxes.length; i += 4) {
+            ctx.beginPath();
+            let x_adj = (censusBoxes[i] - viewX) * cellWidth;
+            let y_adj = (censusBoxes[i + 1] - viewY) * cellHeight;
+            ctx.rect(x_adj, y_adj, censusBoxes[i + 2] * cellWidth, censusBoxes[i + 3] * cellHeight);
+            ctx.stroke();
+        }
     }
 
-    let block_pat = [
-        {x: 0, y: 0},
-        {x: 1, y: 0},
-        {x: 2, y: 0},
-        {x: 2, y: 1},
-        {x: 1, y: 2},
-    ];
-
+    if (gliderMatches !== null) {
+        ctx.strokeStyle = "blue";
+        for (let i = 0; i < gliderMatches.length; i += 3) {
+            ctx.beginPath();
+            let x_adj = (gliderMatches[i] - viewX) * cellWidth;
+            let y_adj = (gliderMatches[i + 1] - viewY) * cellHeight;
+            ctx.rect(x_adj, y_adj, 3 * cellWidth, 3 * cellHeight);
+            ctx.stroke();
+        }
+    }
 }
 
+// Object outlines from the last census, as flattened [x, y, width, height, ...].
+var censusBoxes = null;
+
+// Gliders in any orientation, as flattened [x, y, orientation, ...].
+var gliderMatches = null;
+
 var frameCounter = 0;
 var iterationCounter = 0;
 
@@ -605,6 +616,7 @@ function statsLoop() {
             frameCounter = 0;
             iterationCounter = 0;
         }
+        generationCounterElem.innerText = uni.generation();
         last_poll_time = performance.now();
     });
 }
@@ -622,7 +634,7 @@ function universeLoop() {
             // let x = uni.population();
             // console.log(x);
             // advance 2**j generations
-            uni.advance(j);
+            uni.advance_pow2(j);
             iterationCounter += Math.pow(2, j);
         }
     });
@@ -717,11 +729,7 @@ fileInputElem.addEventListener('change', (event) => {
     const file = event.target.files[0];
     let fReader = new FileReader();
     fReader.onload = function() {
-        let patternData = parseRle(fReader.result);
-        console.log('Parsed RLE:', patternData);
-        patternData.coords.forEach(coord => {
-            uni.set(coord.x, coord.y);
-        });
+        uni.from_rle(fReader.result);
         // let pc = pack_coords(patternData.coords);
         console.log('got pc')
         // uni.set_bulk(pc);
@@ -765,7 +773,8 @@ function loadPatternFromUrl(url){
                         ],
                         rotation: 0,
                     },
-                    data: patternData
+                    data: patternData,
+                    text: request.responseText
                 };
                 // patternData.coords.forEach(coord => {
                 //     uni.set(-coord.x + xoffset, coord.y + yoffset);
@@ -793,6 +802,62 @@ document.addEventListener('keyup', (e) => {
     if (e.code === 'KeyR' && patternOnDeck !== null) {
         patternOnDeck.meta.rotation = (patternOnDeck.meta.rotation + Math.PI/2) % (2 * Math.PI);
     }
+    // With nothing to paste, the same keys transform the pattern itself.
+    if (patternOnDeck === null && !e.ctrlKey) {
+        if (e.code === 'KeyH') {
+            uni.transform(Transform.FlipHorizontal);
+        } else if (e.code === 'KeyV') {
+            uni.transform(Transform.FlipVertical);
+        } else if (e.code === 'KeyR') {
+            uni.transform(Transform.Rotate90);
+        }
+    }
+    if (e.code === 'KeyZ' && e.ctrlKey) {
+        if (e.shiftKey) {
+            uni.redo();
+        } else {
+            uni.undo();
+        }
+    }
+    if (e.code === 'KeyY' && e.ctrlKey) {
+        uni.redo();
+    }
+    if (e.code === 'KeyC' && !e.ctrlKey) {
+        if (censusBoxes === null) {
+            let census = uni.census(64);
+            console.log(census.summary());
+            censusBoxes = census.boxes();
+            census.free();
+        } else {
+            censusBoxes = null;
+        }
+    }
+    if (e.code === 'KeyG') {
+        if (gliderMatches === null) {
+            let search = Search.from_rle("x = 3, y = 3\nbo$2bo$3o!");
+            search.set_all_orientations(true);
+            gliderMatches = uni.find_pattern(search);
+            search.free();
+        } else {
+            gliderMatches = null;
+        }
+    }
+    if (e.code === 'KeyS' && !e.ctrlKey) {
+        // A fresh 16x16 soup around the origin; the seed is logged so it can be replayed.
+        let seed = BigInt(Math.floor(Math.random() * 2 ** 32));
+        console.log('soup seed', seed.toString());
+        uni.random_fill(-8, -8, 7, 7, 0.5, seed);
+    }
+    if (e.code === 'KeyF') {
+        let view = uni.fit_view(canvasElem.width, canvasElem.height);
+        if (view) {
+            viewX = view.x;
+            viewY = view.y;
+            viewWidth = view.width;
+            viewHeight = view.height;
+            view.free();
+        }
+    }
     if (e.code === 'Space') {
         if (ipsInput.value == 0) {
             ipsInput.value = previousIPS ? previousIPS : 1;